@@ -37,7 +37,7 @@ fi
 
 
 # Build common QEMU command components
-QEMU_BASE="qemu-system-riscv64 -machine virt -cpu rv64 -smp 4 -m 128M -nographic -serial mon:stdio -bios none -device virtio-keyboard-device -kernel"
+QEMU_BASE="qemu-system-riscv64 -machine virt -cpu rv64,zkr=on -smp 4 -m 128M -nographic -serial mon:stdio -bios none -device virtio-keyboard-device -kernel"
 
 EXPECTED_TARGET_PATH=$SCRIPT_DIR/../../target/riscv64gc-unknown-none-elf/debug/walnut
 
@@ -1,5 +1,7 @@
 .section .bss
 	.equ CPU_CNT, 4
+	# a 16KiB stack, and a 4KiB guard page
+	.equ STACK_SLOT_SIZE, 4096 * 5
 .align 16
 	stack0: .space 4096 * CPU_CNT
 
@@ -10,15 +12,18 @@
 # All this does is load the stack pointer from
 # where its calculated to be at compile-time (see kernel.ld)
 # Each hart will run here.
+#
+# We are entered with our hart ID in a0 and the address
+# of the device tree in a1, which are passed on to `kinit`
+# so we only use temporaries here.
 _entry:
-	# Index into the STACK0 byte array as
-	# defined in `init/mod.rs`
+	# Each hart's stack sits below the previous hart's,
+	# with a guard page in between, see `cpu/stack.rs`
 	la sp, __kernel_stack_end 
-        li a0, 1024*4
-        csrr a1, mhartid
-        addi a1, a1, 1
-        mul a0, a0, a1
-        sub sp, sp, a0
+        li t0, STACK_SLOT_SIZE
+        csrr t1, mhartid
+        mul t0, t0, t1
+        sub sp, sp, t0
 	call kinit
 
 spin:
@@ -2,4 +2,5 @@ use core::arch::global_asm;
 
 global_asm!(include_str!("entry.s"));
 global_asm!(include_str!("trap.s"));
+global_asm!(include_str!("switch.s"));
 global_asm!(include_str!("exports.s"));
@@ -0,0 +1,48 @@
+.section .text
+.global switch_context
+.global thread_trampoline
+
+# switch_context(old: *mut Context, new: *const Context)
+#
+# Save the callee-saved registers into `old` and load them from `new`.
+# Everything else was already saved by our caller, as the calling
+# convention requires, so returning "from" this call on the new
+# thread's stack picks up wherever it last switched away.
+switch_context:
+        sd ra, 0(a0)
+        sd sp, 8(a0)
+        sd s0, 16(a0)
+        sd s1, 24(a0)
+        sd s2, 32(a0)
+        sd s3, 40(a0)
+        sd s4, 48(a0)
+        sd s5, 56(a0)
+        sd s6, 64(a0)
+        sd s7, 72(a0)
+        sd s8, 80(a0)
+        sd s9, 88(a0)
+        sd s10, 96(a0)
+        sd s11, 104(a0)
+
+        ld ra, 0(a1)
+        ld sp, 8(a1)
+        ld s0, 16(a1)
+        ld s1, 24(a1)
+        ld s2, 32(a1)
+        ld s3, 40(a1)
+        ld s4, 48(a1)
+        ld s5, 56(a1)
+        ld s6, 64(a1)
+        ld s7, 72(a1)
+        ld s8, 80(a1)
+        ld s9, 88(a1)
+        ld s10, 96(a1)
+        ld s11, 104(a1)
+
+        ret
+
+# A new thread's first switch "returns" here, with its
+# entry point in s1, see `Thread::new` in `task/mod.rs`
+thread_trampoline:
+        mv a0, s1
+        j thread_start
@@ -2,12 +2,16 @@
 .global kernelvec
 .align 4
 kernelvec:
+        # switch to this hart's trap stack, so we can still
+        # handle a trap caused by overflowing the kernel stack.
+        # sscratch now holds the interrupted stack pointer.
+        csrrw sp, sscratch, sp
+
         # make room to save registers.
         addi sp, sp, -256
 
         # save the registers.
         sd ra, 0(sp)
-        sd sp, 8(sp)
         sd gp, 16(sp)
         sd tp, 24(sp)
         sd t0, 32(sp)
@@ -38,11 +42,18 @@ kernelvec:
         sd t5, 232(sp)
         sd t6, 240(sp)
 
+        # the interrupted stack pointer
+        csrr t0, sscratch
+        sd t0, 8(sp)
+
         call handle_trap
 
+        # reset sscratch to the top of the trap stack for the next trap
+        addi t0, sp, 256
+        csrw sscratch, t0
+
         # restore registers.
         ld ra, 0(sp)
-        ld sp, 8(sp)
         ld gp, 16(sp)
         # not tp (contains hartid), in case we moved CPUs
         ld t0, 32(sp)
@@ -73,7 +84,8 @@ kernelvec:
         ld t5, 232(sp)
         ld t6, 240(sp)
 
-        addi sp, sp, 256
+        # back onto the interrupted stack
+        ld sp, 8(sp)
 
         # return to whatever we were doing in the kernel.
         sret
@@ -0,0 +1,60 @@
+//! Stack smashing protection, for kernels built with `-Z stack-protector`.
+//!
+//! Instrumented functions copy [`__stack_chk_guard`] below their locals
+//! and check it is unchanged before returning, calling
+//! [`__stack_chk_fail`] if an overflow has overwritten it. On RISC-V the
+//! compiler reads the guard from a global rather than through `tp`,
+//! so every hart shares one guard instead of each keeping its own.
+//!
+//! The protector is off by default, add `-Zstack-protector=strong`
+//! to the `rustflags` in `.cargo/config.toml` to turn it on.
+
+use core::{
+    ptr::addr_of_mut,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::random;
+
+/// The guard until [`init`] picks one, and if there's no entropy source.
+/// The low byte is zero, so a string copy running off the end of a
+/// buffer can't write the guard back.
+const DEFAULT_GUARD: usize = 0x595e_9fbd_94fd_a700;
+
+#[no_mangle]
+static mut __stack_chk_guard: usize = DEFAULT_GUARD;
+
+/// Set once the guard won't change again
+static GUARD_SET: AtomicBool = AtomicBool::new(false);
+
+/// Pick a random guard, on the main hart.
+///
+/// Any instrumented frame live across this will fail its check, so the
+/// other harts must be held in [`wait`] until it's done, and it must
+/// not have locals the protector would guard itself.
+pub fn init() {
+    if let Some(seed) = random::hardware_u64() {
+        unsafe { addr_of_mut!(__stack_chk_guard).write_volatile(seed as usize & !0xff) };
+    }
+    GUARD_SET.store(true, Ordering::Release);
+}
+
+/// Wait for the main hart to pick the guard.
+pub fn wait() {
+    while !GUARD_SET.load(Ordering::Acquire) {
+        core::hint::spin_loop();
+    }
+}
+
+/// The value instrumented functions check against
+pub fn guard() -> usize {
+    unsafe { addr_of_mut!(__stack_chk_guard).read_volatile() }
+}
+
+/// Called by an instrumented function whose guard was overwritten.
+///
+/// Its frame can't be trusted, so we mustn't return into it.
+#[no_mangle]
+pub extern "C" fn __stack_chk_fail() -> ! {
+    panic!("stack smashing detected")
+}
@@ -34,6 +34,9 @@ pub enum ControlStatusRegister {
     /// Physical Memory Protection address
     Pmpaddr0,
 
+    /// Physical Memory Protection address, for the second entry
+    Pmpaddr1,
+
     /// Physical Memory Protection Configuration
     Pmpcfg0,
 
@@ -46,9 +49,31 @@ pub enum ControlStatusRegister {
     /// Supervisor Cause
     Scause,
 
+    /// Supervisor Scratch, holding the top of this hart's trap stack
+    Sscratch,
+
     /// Supervisor Status
     SStatus,
 
+    /// Supervisor Timer Compare, from the Sstc extension
+    Stimecmp,
+
+    /// Machine Environment Configuration
+    Menvcfg,
+
+    /// Machine Counter Enable
+    Mcounteren,
+
+    /// Machine Security Configuration, from Smepmp/Zkr
+    Mseccfg,
+
+    /// Entropy source, from the Zkr extension.
+    /// Each read consumes the entropy it returns.
+    Seed,
+
+    /// Real-time counter, read only
+    Time,
+
     /// Thread Pointer
     /// NOTE: this is not actually a CSR, but we currently
     /// mostly use it like one, so its here.
@@ -69,11 +94,21 @@ impl ControlStatusRegister {
                 Self::Mideleg => core::arch::asm!("csrr {0}, mideleg", out(reg) result),
                 Self::Sie => core::arch::asm!("csrr {0}, sie", out(reg) result),
                 Self::Pmpaddr0 => core::arch::asm!("csrr {0}, pmpaddr0", out(reg) result),
+                Self::Pmpaddr1 => core::arch::asm!("csrr {0}, pmpaddr1", out(reg) result),
                 Self::Pmpcfg0 => core::arch::asm!("csrr {0}, pmpcfg0", out(reg) result),
                 Self::Stvec => core::arch::asm!("csrr {0}, stvec", out(reg) result),
                 Self::Stval => core::arch::asm!("csrr {0}, stval", out(reg) result),
                 Self::Scause => core::arch::asm!("csrr {0}, scause", out(reg) result),
+                Self::Sscratch => core::arch::asm!("csrr {0}, sscratch", out(reg) result),
                 Self::SStatus => core::arch::asm!("csrr {0}, sstatus", out(reg) result),
+                // Not all assemblers know the newer CSR names, so use their numbers
+                Self::Stimecmp => core::arch::asm!("csrr {0}, 0x14d", out(reg) result),
+                Self::Menvcfg => core::arch::asm!("csrr {0}, 0x30a", out(reg) result),
+                Self::Mcounteren => core::arch::asm!("csrr {0}, mcounteren", out(reg) result),
+                Self::Mseccfg => core::arch::asm!("csrr {0}, 0x747", out(reg) result),
+                // `seed` must be accessed with a read-write instruction
+                Self::Seed => core::arch::asm!("csrrw {0}, 0x015, zero", out(reg) result),
+                Self::Time => core::arch::asm!("csrr {0}, time", out(reg) result),
                 Self::Mhartid => core::arch::asm!("csrr {0}, mhartid", out(reg) result),
                 Self::ThreadPointer => core::arch::asm!("mv {0}, tp", out(reg) result),
             }
@@ -92,11 +127,19 @@ impl ControlStatusRegister {
                 Self::Mideleg => core::arch::asm!("csrw mideleg, {}", in(reg) v),
                 Self::Sie => core::arch::asm!("csrw sie, {}", in(reg) v),
                 Self::Pmpaddr0 => core::arch::asm!("csrw  pmpaddr0, {}", in(reg) v),
+                Self::Pmpaddr1 => core::arch::asm!("csrw  pmpaddr1, {}", in(reg) v),
                 Self::Pmpcfg0 => core::arch::asm!("csrw  pmpcfg0, {}", in(reg) v),
                 Self::Stvec => core::arch::asm!("csrw  stvec, {}", in(reg) v),
                 Self::Stval => core::arch::asm!("csrw  stval, {}", in(reg) v),
                 Self::Scause => core::arch::asm!("csrw  scause, {}", in(reg) v),
+                Self::Sscratch => core::arch::asm!("csrw  sscratch, {}", in(reg) v),
                 Self::SStatus => core::arch::asm!("csrw  sstatus, {}", in(reg) v),
+                Self::Stimecmp => core::arch::asm!("csrw  0x14d, {}", in(reg) v),
+                Self::Menvcfg => core::arch::asm!("csrw  0x30a, {}", in(reg) v),
+                Self::Mcounteren => core::arch::asm!("csrw  mcounteren, {}", in(reg) v),
+                Self::Mseccfg => core::arch::asm!("csrw  0x747, {}", in(reg) v),
+                Self::Seed => unreachable!("writes to seed are ignored"),
+                Self::Time => unreachable!("time is a read-only CSR"),
                 Self::Mhartid => core::arch::asm!("csrw  mhartid, {}", in(reg) v),
                 Self::ThreadPointer => core::arch::asm!("mv  tp, {}", in(reg) v),
             }
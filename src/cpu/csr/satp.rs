@@ -1 +1,59 @@
+//! Typed access to the Supervisor Address Translation and Protection register.
+//!
+//! `satp` holds the physical page number of the root page table, the
+//! current address space ID, and which translation scheme is active.
+//! Switching address spaces is just writing a new `Satp` value.
 
+use mycelium_bitfield::bitfield;
+
+use super::ControlStatusRegister;
+
+bitfield! {
+    pub struct Satp<usize> {
+        /// Physical page number of the root page table
+        pub const PPN = 44;
+
+        /// Address Space Identifier
+        pub const ASID = 16;
+
+        /// Address translation scheme, see [`Satp::MODE_SV39`]
+        pub const MODE = 4;
+    }
+}
+
+impl Satp {
+    /// No translation, virtual addresses are physical addresses.
+    pub const MODE_BARE: usize = 0;
+
+    /// Page-based 39-bit virtual addressing
+    pub const MODE_SV39: usize = 8;
+
+    /// Read the current value of `satp`
+    pub fn read() -> Self {
+        Self::from_bits(ControlStatusRegister::Satp.read())
+    }
+
+    /// Write this value into `satp`, switching to its address space.
+    ///
+    /// NOTE: This does not flush any stale translations.
+    pub fn write(self) {
+        ControlStatusRegister::Satp.write(self.bits());
+    }
+
+    /// Build a `satp` value for an Sv39 root table at the physical address `root`
+    pub fn sv39(root: usize, asid: usize) -> Self {
+        Self::new()
+            .with(Self::PPN, root >> 12)
+            .with(Self::ASID, asid)
+            .with(Self::MODE, Self::MODE_SV39)
+    }
+
+    /// Physical address of the root page table
+    pub fn root_table(&self) -> usize {
+        self.get(Self::PPN) << 12
+    }
+
+    pub fn paging_enabled(&self) -> bool {
+        self.get(Self::MODE) != Self::MODE_BARE
+    }
+}
@@ -1 +1,71 @@
+//! Typed access to the Supervisor Status register.
+//!
+//! `sstatus` is a restricted view of `mstatus`, holding the
+//! global interrupt enable, the previous privilege mode
+//! and the memory access modifiers (`SUM`, `MXR`).
 
+use mycelium_bitfield::bitfield;
+
+use super::ControlStatusRegister;
+
+bitfield! {
+    pub struct SStatus<usize> {
+        const _WPRI0 = 1;
+
+        /// Supervisor Interrupt Enable
+        pub const SIE: bool;
+
+        const _WPRI1 = 3;
+
+        /// Supervisor Previous Interrupt Enable
+        pub const SPIE: bool;
+
+        /// User-mode Big Endian
+        pub const UBE: bool;
+
+        const _WPRI2 = 1;
+
+        /// Supervisor Previous Privilege, set if we trapped from S-mode
+        pub const SPP: bool;
+
+        /// Vector extension state
+        pub const VS = 2;
+
+        const _WPRI3 = 2;
+
+        /// Floating point unit state
+        pub const FS = 2;
+
+        /// Additional user-mode extension state
+        pub const XS = 2;
+
+        const _WPRI4 = 1;
+
+        /// Permit Supervisor User Memory access
+        pub const SUM: bool;
+
+        /// Make eXecutable Readable
+        pub const MXR: bool;
+
+        const _WPRI5 = 12;
+
+        /// User-mode XLEN
+        pub const UXL = 2;
+
+        const _WPRI6 = 29;
+
+        /// Set if any of `FS`, `VS` or `XS` are dirty
+        pub const SD: bool;
+    }
+}
+
+impl SStatus {
+    /// Read the current value of `sstatus`
+    pub fn read() -> Self {
+        Self::from_bits(ControlStatusRegister::SStatus.read())
+    }
+
+    pub fn write(self) {
+        ControlStatusRegister::SStatus.write(self.bits());
+    }
+}
@@ -0,0 +1,46 @@
+//! Enabling and disabling supervisor interrupts on the current hart.
+//!
+//! `push_off`/`pop_off` work like xv6's: they nest, and
+//! interrupts are only re-enabled once the outermost `pop_off`
+//! is reached, and only if they were enabled to begin with.
+
+use core::sync::atomic::Ordering;
+
+use super::{csr::status::SStatus, percpu::this_hart};
+
+pub fn enable() {
+    SStatus::read().with(SStatus::SIE, true).write();
+}
+
+pub fn disable() {
+    SStatus::read().with(SStatus::SIE, false).write();
+}
+
+pub fn enabled() -> bool {
+    SStatus::read().get(SStatus::SIE)
+}
+
+/// Disable interrupts, remembering if they were enabled.
+pub fn push_off() {
+    let was_enabled = enabled();
+    disable();
+
+    let hart = this_hart();
+    if hart.off_depth.fetch_add(1, Ordering::Relaxed) == 0 {
+        hart.were_enabled.store(was_enabled, Ordering::Relaxed);
+    }
+}
+
+/// Undo a `push_off`, re-enabling interrupts if this
+/// is the outermost one and they were enabled before.
+pub fn pop_off() {
+    assert!(!enabled(), "pop_off with interrupts enabled");
+
+    let hart = this_hart();
+    let prev = hart.off_depth.fetch_sub(1, Ordering::Relaxed);
+    assert!(prev != 0, "pop_off without a matching push_off");
+
+    if prev == 1 && hart.were_enabled.load(Ordering::Relaxed) {
+        enable();
+    }
+}
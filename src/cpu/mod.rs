@@ -1,15 +1,58 @@
-use core::arch::asm;
+use core::{arch::asm, sync::atomic::{AtomicUsize, Ordering}};
 
 use crate::cpu::csr::ControlStatusRegister;
 
 use self::mode::Mode;
 
+pub mod canary;
 pub mod csr;
+pub mod interrupts;
 pub mod mode;
+pub mod percpu;
 pub mod port;
+pub mod random;
+pub mod stack;
+pub mod timer;
 pub mod trap;
 pub mod util;
 
+/// Maximum number of hardware threads we support,
+/// this must match `CPU_CNT` in `entry.s`
+pub const MAX_HARTS: usize = 4;
+
+/// Harts that have finished their initialization
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of harts the device tree describes,
+/// 1 until the main hart has read it.
+pub fn cpu_count() -> usize {
+    crate::init::boot_info::get().map_or(1, |info| info.topology.hart_count())
+}
+
+/// Count this hart as up and ready to take work
+pub fn mark_online() {
+    ONLINE_HARTS.fetch_add(1, Ordering::Release);
+}
+
+/// Number of harts that have finished their initialization
+pub fn online_harts() -> usize {
+    ONLINE_HARTS.load(Ordering::Acquire)
+}
+
+/// Wait up to `timeout` timer ticks for every hart to come online,
+/// returning how many did.
+///
+/// Every hart starts at `_entry` at once, so this only has to wait for
+/// them to get through their initialization. The ticks are counted by
+/// hart 0, so it must have interrupts enabled or this waits forever.
+pub fn wait_for_harts(timeout: u64) -> usize {
+    let deadline = timer::uptime_ticks() + timeout;
+    while online_harts() < cpu_count() && timer::uptime_ticks() < deadline {
+        core::hint::spin_loop();
+    }
+    online_harts()
+}
+
 /// Delete exceptions and interrupts to Supervisor mode
 pub fn delegate_traps() {
     ControlStatusRegister::Medeleg.write(0xffff);
@@ -51,6 +94,16 @@ pub unsafe fn transition(dest_mode: Mode) -> ! {
     }
 }
 
+/// Idle this hart forever.
+///
+/// `wfi` stalls the hart until an interrupt is pending,
+/// instead of burning cycles in a busy loop.
+pub fn wfi_loop() -> ! {
+    loop {
+        unsafe { asm!("wfi") }
+    }
+}
+
 pub fn save_hartid() {
     ControlStatusRegister::ThreadPointer.write(ControlStatusRegister::Mhartid.read());
 }
@@ -0,0 +1,71 @@
+//! State kept separately for each hart.
+//!
+//! `tp` holds the running hart's ID (see [`save_hartid`](super::save_hartid)),
+//! which we use to index into a static block per hart, much like
+//! x86 uses `gs` to find its per-CPU data.
+//!
+//! Each block is only ever touched by its own hart, the atomics
+//! are just so the blocks can be shared in a `static`.
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use super::{util::my_hart, MAX_HARTS};
+
+pub struct PerHart {
+    /// Set by [`init_hart`], so we can check our block is really ours
+    id: AtomicUsize,
+    /// How many `push_off`s deep this hart is
+    pub(crate) off_depth: AtomicUsize,
+    /// Whether interrupts were enabled before the outermost `push_off`
+    pub(crate) were_enabled: AtomicBool,
+    /// Bounds of the running thread's stacks, zero
+    /// until the hart first switches threads
+    pub(crate) stack_start: AtomicUsize,
+    pub(crate) stack_end: AtomicUsize,
+}
+
+impl PerHart {
+    const fn new() -> Self {
+        Self {
+            id: AtomicUsize::new(usize::MAX),
+            off_depth: AtomicUsize::new(0),
+            were_enabled: AtomicBool::new(false),
+            stack_start: AtomicUsize::new(0),
+            stack_end: AtomicUsize::new(0),
+        }
+    }
+
+    /// The ID of the hart this block belongs to
+    pub fn id(&self) -> usize {
+        self.id.load(Ordering::Relaxed)
+    }
+
+    /// Where the thread running on this hart keeps its stacks, if it
+    /// isn't a boot thread on the boot and static trap stacks
+    pub fn thread_stack(&self) -> Option<Range<usize>> {
+        let stack = self.stack_start.load(Ordering::Relaxed)..self.stack_end.load(Ordering::Relaxed);
+        (!stack.is_empty()).then_some(stack)
+    }
+}
+
+static HARTS: [PerHart; MAX_HARTS] = [const { PerHart::new() }; MAX_HARTS];
+
+/// Claim this hart's block, once `tp` holds its ID.
+pub fn init_hart() {
+    let hart = unsafe { my_hart() };
+    assert!(hart < MAX_HARTS, "hart {} is beyond the {} we support", hart, MAX_HARTS);
+    HARTS[hart].id.store(hart, Ordering::Relaxed);
+}
+
+/// The running hart's block
+pub fn this_hart() -> &'static PerHart {
+    &HARTS[unsafe { my_hart() }]
+}
+
+/// Another hart's block, e.g. to report on it
+pub fn hart(id: usize) -> Option<&'static PerHart> {
+    HARTS.get(id)
+}
@@ -0,0 +1,75 @@
+//! Hardware entropy, from the Zkr extension's `seed` CSR.
+//!
+//! S-mode may only read `seed` once M-mode sets `mseccfg.SSEED`, which
+//! [`delegate`] does on harts whose device tree node lists `zkr`.
+//! Reading it on any other hart is an illegal instruction.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::{csr::ControlStatusRegister, util::my_hart, MAX_HARTS};
+use crate::fdt::{self, topology::Topology};
+
+/// Give up rather than spin forever on a source that stays busy
+pub const MAX_POLLS: usize = 10_000;
+
+/// Harts [`delegate`] found to implement Zkr
+static HAS_ZKR: [AtomicBool; MAX_HARTS] = [const { AtomicBool::new(false) }; MAX_HARTS];
+
+/// Let S-mode read `seed`, if this hart has it.
+///
+/// This must be called in M-mode, after the device tree address is known.
+pub fn delegate() {
+    const SSEED: usize = 1 << 9;
+
+    let Some(topology) = fdt::get().ok().and_then(|fdt| Topology::from_fdt(&fdt).ok()) else {
+        return;
+    };
+    let hart = unsafe { my_hart() };
+    if topology.harts().any(|h| h.id == hart && h.has_zkr) {
+        ControlStatusRegister::Mseccfg.write(ControlStatusRegister::Mseccfg.read() | SSEED);
+        HAS_ZKR[hart].store(true, Ordering::Relaxed);
+    }
+}
+
+/// 16 bits of entropy, or `None` if the source has failed
+fn seed_u16() -> Option<u16> {
+    seed_from(|| ControlStatusRegister::Seed.read())
+}
+
+/// Poll `read` for a `seed` value holding 16 bits of entropy, giving
+/// up after [`MAX_POLLS`] reads or once the source reports it's dead.
+pub fn seed_from(mut read: impl FnMut() -> usize) -> Option<u16> {
+    const OPST_SHIFT: usize = 30;
+    const BIST: usize = 0b00;
+    const WAIT: usize = 0b01;
+    const ES16: usize = 0b10;
+
+    for _ in 0..MAX_POLLS {
+        let seed = read();
+        match (seed >> OPST_SHIFT) & 0b11 {
+            ES16 => return Some(seed as u16),
+            // still warming up or gathering entropy
+            BIST | WAIT => core::hint::spin_loop(),
+            // DEAD, the source won't recover
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// 64 bits of hardware entropy, or `None` if this hart doesn't
+/// implement Zkr, or its entropy source has failed.
+///
+/// This is only known once [`delegate`] has run on this hart,
+/// after which it works in both M-mode and S-mode.
+pub fn hardware_u64() -> Option<u64> {
+    if !HAS_ZKR[unsafe { my_hart() }].load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let mut value = 0;
+    for _ in 0..4 {
+        value = value << 16 | seed_u16()? as u64;
+    }
+    Some(value)
+}
@@ -0,0 +1,81 @@
+//! Per-hart kernel and trap stacks, and the guard pages between them.
+//!
+//! Each hart's kernel stack sits at the top of the linker's kernel stack
+//! region, below the previous hart's, with a guard page underneath it.
+//! Physical memory protection denies S-mode all access to the guard
+//! page, so running off the bottom of a stack raises an access fault
+//! instead of silently overwriting the next hart's stack.
+//!
+//! That fault can't be handled on the stack that overflowed, so traps
+//! are handled on a separate per-hart trap stack, which `kernelvec`
+//! switches to through `sscratch`.
+
+use core::ptr::addr_of;
+
+use super::{csr::ControlStatusRegister, util::my_hart, MAX_HARTS};
+
+/// Size of each hart's kernel stack
+pub const STACK_SIZE: usize = 4096 * 4;
+
+/// Size of the inaccessible region below each kernel stack,
+/// a power of two so it can be described by a single PMP entry
+pub const GUARD_SIZE: usize = 4096;
+const _: () = assert!(GUARD_SIZE.is_power_of_two() && GUARD_SIZE >= 8);
+
+/// Space taken by each hart's stack and guard page,
+/// this must match `STACK_SLOT_SIZE` in `entry.s`
+const SLOT_SIZE: usize = STACK_SIZE + GUARD_SIZE;
+const _: () = assert!(SLOT_SIZE == 4096 * 5, "update STACK_SLOT_SIZE in entry.s");
+
+pub const TRAP_STACK_SIZE: usize = 4096 * 2;
+
+#[repr(C, align(16))]
+struct TrapStack([u8; TRAP_STACK_SIZE]);
+
+static mut TRAP_STACKS: [TrapStack; MAX_HARTS] = [const { TrapStack([0; TRAP_STACK_SIZE]) }; MAX_HARTS];
+
+/// Top of `hart`'s kernel stack, where its stack pointer starts
+pub fn stack_top(hart: usize) -> usize {
+    unsafe { crate::KERNEL_STACK_END - SLOT_SIZE * hart }
+}
+
+/// Base address of the guard page below `hart`'s kernel stack
+pub fn guard_page(hart: usize) -> usize {
+    stack_top(hart) - SLOT_SIZE
+}
+
+/// The hart whose guard page `addr` falls in, if any
+pub fn guard_page_owner(addr: usize) -> Option<usize> {
+    (0..MAX_HARTS).find(|&hart| (guard_page(hart)..guard_page(hart) + GUARD_SIZE).contains(&addr))
+}
+
+/// Set up physical memory protection so supervisor mode can access all
+/// of physical memory, except for this hart's guard page.
+///
+/// This must be called in M-mode, PMP entries are per hart.
+pub fn protect_guard_page() {
+    const RWX: usize = 0b111;
+    // a naturally aligned power of two region
+    const NAPOT: usize = 0b11 << 3;
+
+    // NAPOT regions are encoded as the base, with the low bits
+    // set to one less than half the size in 4 byte units
+    ControlStatusRegister::Pmpaddr0.write((guard_page(unsafe { my_hart() }) >> 2) | (GUARD_SIZE / 8 - 1));
+    // all ones covers the whole address space
+    ControlStatusRegister::Pmpaddr1.write(0x3fffffffffffff);
+
+    // the lowest numbered matching entry wins, so
+    // the guard page (entry 0) takes priority.
+    ControlStatusRegister::Pmpcfg0.write(((NAPOT | RWX) << 8) | NAPOT);
+}
+
+/// Top of the trap stack used by `hart`'s boot thread
+pub fn trap_stack_top(hart: usize) -> usize {
+    unsafe { addr_of!(TRAP_STACKS[hart]) as usize + TRAP_STACK_SIZE }
+}
+
+/// Point `sscratch` at the top of this hart's trap stack, which
+/// must be done before any trap is taken.
+pub fn init_trap_stack() {
+    ControlStatusRegister::Sscratch.write(trap_stack_top(unsafe { my_hart() }));
+}
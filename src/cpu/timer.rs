@@ -0,0 +1,115 @@
+//! Supervisor timer interrupts and the kernel tick counter.
+//!
+//! Rather than bouncing timer interrupts through M-mode, we use
+//! the Sstc extension: each hart arms `stimecmp` itself and gets a
+//! supervisor timer interrupt once `time` passes it.
+
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use super::{csr::ControlStatusRegister, util::my_hart};
+
+/// Frequency of the `time` counter on the QEMU `virt` machine,
+/// used until we [`calibrate`] from the device tree.
+const DEFAULT_TIMEBASE_FREQ: usize = 10_000_000;
+
+/// Default number of timer interrupts per second
+pub const DEFAULT_TICK_HZ: usize = 100;
+
+/// Frequency of the `time` counter, in Hz
+static TIMEBASE_FREQ: AtomicUsize = AtomicUsize::new(DEFAULT_TIMEBASE_FREQ);
+
+/// Timer interrupts per second
+static TICK_HZ: AtomicUsize = AtomicUsize::new(DEFAULT_TICK_HZ);
+
+/// `time` increments between each timer interrupt
+static INTERVAL: AtomicUsize = AtomicUsize::new(DEFAULT_TIMEBASE_FREQ / DEFAULT_TICK_HZ);
+
+/// Ticks since the timer was first armed, counted by hart 0 only
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Allow supervisor mode to use `time` and `stimecmp`
+///
+/// This must be called in M-mode.
+pub fn delegate() {
+    const STCE: usize = 1 << 63;
+    const TM: usize = 1 << 1;
+
+    ControlStatusRegister::Menvcfg.write(ControlStatusRegister::Menvcfg.read() | STCE);
+    ControlStatusRegister::Mcounteren.write(ControlStatusRegister::Mcounteren.read() | TM);
+}
+
+/// Set how many timer interrupts we take per second, from the next tick on.
+pub fn set_frequency(hz: usize) {
+    assert!(hz != 0 && hz <= timebase_frequency());
+    TICK_HZ.store(hz, Ordering::Relaxed);
+    INTERVAL.store(timebase_frequency() / hz, Ordering::Relaxed);
+}
+
+/// Timer interrupts per second
+pub fn frequency() -> usize {
+    TICK_HZ.load(Ordering::Relaxed)
+}
+
+/// Set the frequency the `time` counter runs at, as reported by
+/// the `timebase-frequency` of the device tree's `/cpus` node.
+pub fn calibrate(timebase_hz: usize) {
+    assert!(timebase_hz != 0);
+    TIMEBASE_FREQ.store(timebase_hz, Ordering::Relaxed);
+    set_frequency(frequency());
+}
+
+/// Frequency of the `time` counter, in Hz
+pub fn timebase_frequency() -> usize {
+    TIMEBASE_FREQ.load(Ordering::Relaxed)
+}
+
+/// Arm this hart's timer for the first tick.
+pub fn init_hart() {
+    arm();
+}
+
+fn arm() {
+    ControlStatusRegister::Stimecmp
+        .write(ControlStatusRegister::Time.read() + INTERVAL.load(Ordering::Relaxed));
+}
+
+/// Called on every supervisor timer interrupt
+pub fn handle_interrupt() {
+    if unsafe { my_hart() } == 0 {
+        TICKS.fetch_add(1, Ordering::Relaxed);
+
+        // a no-op unless a test is running, integration test
+        // binaries link the kernel without `cfg(test)`
+        crate::testing::check_watchdog();
+    }
+    // re-arming also clears the pending interrupt
+    arm();
+}
+
+/// Timer ticks since boot
+pub fn uptime_ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Wait for at least `n` ticks to pass.
+///
+/// This relies on interrupts being enabled.
+pub fn sleep_ticks(n: u64) {
+    let end = uptime_ticks() + n;
+    while uptime_ticks() < end {
+        core::hint::spin_loop();
+    }
+}
+
+/// Wait for at least `ms` milliseconds.
+///
+/// This counts `time` directly rather than ticks, so it works with
+/// interrupts disabled and isn't rounded to the tick interval.
+pub fn sleep(ms: u64) {
+    let start = ControlStatusRegister::Time.read() as u64;
+    let wait = ms.saturating_mul(timebase_frequency() as u64) / 1000;
+    // the difference is still right if `time` wraps while we wait
+    while (ControlStatusRegister::Time.read() as u64).wrapping_sub(start) < wait {
+        core::hint::spin_loop();
+    }
+}
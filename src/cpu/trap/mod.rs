@@ -31,6 +31,16 @@ pub enum Exception {
     InstructionAccessFault,
     IllegalInstruction,
     Breakpoint,
+    LoadAddressMisaligned,
+    LoadAccessFault,
+    StoreAddressMisaligned,
+    StoreAccessFault,
+    UserEnvironmentCall,
+    SupervisorEnvironmentCall,
+    InstructionPageFault,
+    LoadPageFault,
+    StorePageFault,
+    Reserved(usize),
 }
 
 impl From<usize> for Exception {
@@ -41,7 +51,17 @@ impl From<usize> for Exception {
             0 => Self::InstructionAddressMisaligned,
             1 => Self::InstructionAccessFault,
             2 => Self::IllegalInstruction,
-            _ => todo!(),
+            3 => Self::Breakpoint,
+            4 => Self::LoadAddressMisaligned,
+            5 => Self::LoadAccessFault,
+            6 => Self::StoreAddressMisaligned,
+            7 => Self::StoreAccessFault,
+            8 => Self::UserEnvironmentCall,
+            9 => Self::SupervisorEnvironmentCall,
+            12 => Self::InstructionPageFault,
+            13 => Self::LoadPageFault,
+            15 => Self::StorePageFault,
+            code => Self::Reserved(code),
         }
     }
 }
@@ -54,12 +74,13 @@ extern "C" fn handle_trap()
     let sepc = ControlStatusRegister::Sepc.read();
     let status = ControlStatusRegister::SStatus.read(); 
     let scause = ControlStatusRegister::Scause.read(); 
-    let trap_val = ControlStatusRegister::Scause.read(); 
-    crate::info!("SEPC={:#0x} SSTATUS={:#0x} SCAUSE={:#0x} STVAL={:#0x} ", sepc, status, scause, trap_val);
+    let trap_val = ControlStatusRegister::Stval.read();
 
     if is_interrupt(scause) {
+        // interrupts are frequent (timer ticks), so dont log them all
         handle_interrupt()
     } else {
+        crate::info!("SEPC={:#0x} SSTATUS={:#0x} SCAUSE={:#0x} STVAL={:#0x} ", sepc, status, scause, trap_val);
         handle_exception()
     }
 }
@@ -67,7 +88,19 @@ extern "C" fn handle_trap()
 fn handle_interrupt() {
     let scause = ControlStatusRegister::Scause.read(); 
 
-    debug!("Interrupt: {:?}", Interrupt::from(scause));
+    let interrupt = Interrupt::from(scause);
+
+    match interrupt {
+        Interrupt::External => crate::drivers::handle_external_interrupt(),
+        Interrupt::Timer => handle_timer_interrupt(),
+        _ => debug!("Interrupt: {:?}", interrupt),
+    }
+}
+
+fn handle_timer_interrupt() {
+    super::timer::handle_interrupt();
+    // give the next thread a turn
+    crate::task::preempt();
 }
 fn handle_exception() {
     use Exception::*;
@@ -81,6 +114,15 @@ fn handle_exception() {
         IllegalInstruction => {
             panic!("ILLEGAL INSTRUCTION DETECTED");
         },
+        LoadAccessFault | StoreAccessFault => {
+            let addr = ControlStatusRegister::Stval.read();
+            if let Some(hart) = super::stack::guard_page_owner(addr) {
+                panic!("KERNEL STACK OVERFLOW: hart {} accessed {:#x}, in its stack's guard page", hart, addr);
+            }
+            // skipping the access would carry on with whatever garbage
+            // was left in its destination register
+            panic!("{:?}: STVAL={:#x} SEPC={:#x}", exception, addr, ControlStatusRegister::Sepc.read());
+        },
         _ => {},
     }
 
@@ -0,0 +1,77 @@
+//! Where `print!` output goes.
+//!
+//! Output is written to every registered [`OutputSink`], starting with
+//! just the serial console, so more can be added or removed at runtime.
+
+use core::fmt;
+
+use crate::{
+    sync::spinlock::{Guard, SpinLock},
+    util::error::WalnutError,
+};
+
+/// Most sinks that can be registered at once
+pub const MAX_SINKS: usize = 4;
+
+pub trait OutputSink: Sync {
+    fn write_str(&self, s: &str);
+}
+
+/// The UART, see [`super::serial`]
+pub struct SerialSink;
+
+impl OutputSink for SerialSink {
+    fn write_str(&self, s: &str) {
+        let _ = fmt::Write::write_str(&mut *super::serial().lock(), s);
+    }
+}
+
+pub static SERIAL_SINK: SerialSink = SerialSink;
+
+type Sinks = [Option<&'static dyn OutputSink>; MAX_SINKS];
+
+static SINKS: SpinLock<Sinks> = SpinLock::new([Some(&SERIAL_SINK), None, None, None]);
+
+/// Send output to `sink` as well
+pub fn register(sink: &'static dyn OutputSink) -> crate::Result<()> {
+    let mut sinks = SINKS.lock();
+    let slot = sinks
+        .iter_mut()
+        .find(|slot| slot.is_none())
+        .ok_or(WalnutError::new("too many output sinks"))?;
+    *slot = Some(sink);
+    Ok(())
+}
+
+/// Stop sending output to `sink`, returning `false` if it wasn't registered
+pub fn unregister(sink: &'static dyn OutputSink) -> bool {
+    let mut sinks = SINKS.lock();
+    match sinks.iter_mut().find(|slot| slot.is_some_and(|s| core::ptr::addr_eq(s, sink))) {
+        Some(slot) => {
+            *slot = None;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Every sink, locked so a whole `print!` comes out together
+pub struct Console(Guard<'static, Sinks>);
+
+pub fn lock() -> Console {
+    Console(SINKS.lock())
+}
+
+/// The console, unless something else is printing
+pub fn try_lock() -> Option<Console> {
+    SINKS.try_lock().map(Console)
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for sink in self.0.iter().flatten() {
+            sink.write_str(s);
+        }
+        Ok(())
+    }
+}
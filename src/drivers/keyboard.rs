@@ -0,0 +1,191 @@
+//! Keyboard input, as received over the serial console.
+//!
+//! The terminal on the other end of the UART sends us already
+//! translated bytes: plain ASCII for most keys, control characters
+//! for enter/backspace/tab, and ANSI escape sequences for keys like
+//! the arrows. We decode those into [`DecodedKey`]s and queue them up
+//! from the UART interrupt handler for [`read_key`] to pick up,
+//! or for a [`KeyStream`] to hand to an async task.
+
+use core::{
+    async_iter::AsyncIterator,
+    future::poll_fn,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use crate::sync::{ring::RingBuffer, spinlock::SpinLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodedKey {
+    Char(char),
+    Special(SpecialKey),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKey {
+    Enter,
+    Backspace,
+    Tab,
+    Escape,
+    ArrowUp,
+    ArrowDown,
+    ArrowRight,
+    ArrowLeft,
+    Home,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    /// We received an `ESC`
+    Escape,
+    /// We received `ESC [`, the Control Sequence Introducer
+    Csi,
+}
+
+/// Turns a stream of bytes from the terminal into keys
+pub struct Decoder {
+    state: State,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+        }
+    }
+
+    /// Feed the next byte received, returning a key
+    /// if it completes one.
+    ///
+    /// NOTE: A lone `ESC` can't be told apart from the start of
+    /// an escape sequence, so it is only reported once the next byte
+    /// arrives, and that byte is dropped.
+    pub fn feed(&mut self, byte: u8) -> Option<DecodedKey> {
+        use SpecialKey::*;
+
+        match (self.state, byte) {
+            (State::Ground, 0x1b) => {
+                self.state = State::Escape;
+                None
+            }
+            (State::Ground, b'\r' | b'\n') => Some(DecodedKey::Special(Enter)),
+            (State::Ground, 0x7f | 0x08) => Some(DecodedKey::Special(Backspace)),
+            (State::Ground, b'\t') => Some(DecodedKey::Special(Tab)),
+            (State::Ground, 0x20..=0x7e) => Some(DecodedKey::Char(byte as char)),
+            (State::Ground, _) => None,
+
+            (State::Escape, b'[') => {
+                self.state = State::Csi;
+                None
+            }
+            (State::Escape, _) => {
+                self.state = State::Ground;
+                Some(DecodedKey::Special(Escape))
+            }
+
+            (State::Csi, _) => {
+                self.state = State::Ground;
+                let key = match byte {
+                    b'A' => ArrowUp,
+                    b'B' => ArrowDown,
+                    b'C' => ArrowRight,
+                    b'D' => ArrowLeft,
+                    b'H' => Home,
+                    b'F' => End,
+                    _ => return None,
+                };
+                Some(DecodedKey::Special(key))
+            }
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static DECODER: SpinLock<Decoder> = SpinLock::new(Decoder::new());
+
+/// Keys decoded by the UART interrupt handler, waiting to be read
+static KEYS: RingBuffer<DecodedKey, 64> = RingBuffer::new();
+
+/// The task waiting on a [`KeyStream`], woken when a key is queued
+static KEY_WAKER: SpinLock<Option<Waker>> = SpinLock::new(None);
+
+/// Decode a byte received over the UART, queueing up any completed key.
+///
+/// This is the only producer for the key queue, and must be
+/// called from the UART interrupt handler.
+pub fn handle_byte(byte: u8) {
+    // holding the decoder lock while pushing keeps us
+    // the only producer, even if harts race on the interrupt
+    let mut decoder = DECODER.lock();
+    if let Some(key) = decoder.feed(byte) {
+        if KEYS.push(key).is_err() {
+            crate::warn!("Keyboard queue full, dropping {:?}", key);
+        } else if let Some(waker) = KEY_WAKER.lock().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Take the next key pressed, if there is one.
+pub fn read_key() -> Option<DecodedKey> {
+    KEYS.pop()
+}
+
+/// Keys pressed, as an async stream.
+///
+/// This consumes the same queue as [`read_key`],
+/// so only one of them should be used.
+pub struct KeyStream {
+    _private: (),
+}
+
+impl KeyStream {
+    /// Panics if called more than once, the queue
+    /// only supports a single consumer.
+    pub fn new() -> Self {
+        static TAKEN: AtomicBool = AtomicBool::new(false);
+        assert!(!TAKEN.swap(true, Ordering::AcqRel), "KeyStream::new must only be called once");
+        Self { _private: () }
+    }
+
+    /// Wait for the next key
+    pub async fn next(&mut self) -> Option<DecodedKey> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl Default for KeyStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncIterator for KeyStream {
+    type Item = DecodedKey;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DecodedKey>> {
+        // fast path, don't bother registering a waker
+        if let Some(key) = KEYS.pop() {
+            return Poll::Ready(Some(key));
+        }
+
+        *KEY_WAKER.lock() = Some(cx.waker().clone());
+        // a key might have been queued before the waker was registered
+        match KEYS.pop() {
+            Some(key) => {
+                KEY_WAKER.lock().take();
+                Poll::Ready(Some(key))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Memory mapped device registers.
+//!
+//! Device registers must be accessed with volatile reads and writes,
+//! otherwise the compiler is free to merge, reorder or drop them.
+
+use core::{cell::UnsafeCell, marker::PhantomData};
+
+/// A memory mapped register holding a `T`, or the first
+/// of a block of them.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct Mmio<T> {
+    addr: usize,
+    _ty: PhantomData<T>,
+}
+
+impl<T> Clone for Mmio<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Mmio<T> {}
+
+impl<T: Copy> Mmio<T> {
+    pub const fn new(addr: usize) -> Self {
+        Self {
+            addr,
+            _ty: PhantomData,
+        }
+    }
+
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// The `n`th register of a block of `T`s starting here
+    pub const fn reg(&self, n: usize) -> Self {
+        Self::new(self.addr + n * core::mem::size_of::<T>())
+    }
+
+    /// A register `bytes` past this one
+    pub const fn offset(&self, bytes: usize) -> Self {
+        Self::new(self.addr + bytes)
+    }
+
+    /// # Safety
+    ///
+    /// There must be a readable device register of type `T` at this address.
+    pub unsafe fn read(&self) -> T {
+        (self.addr as *const T).read_volatile()
+    }
+
+    /// # Safety
+    ///
+    /// There must be a writable device register of type `T` at this address,
+    /// and writing to it can have any side effect the device has.
+    pub unsafe fn write(&self, value: T) {
+        (self.addr as *mut T).write_volatile(value)
+    }
+}
+
+/// A single field of a `#[repr(C)]` register block, only ever
+/// accessed with volatile reads and writes.
+///
+/// Once a reference to the block is made, its fields can be used
+/// safely, unlike [`Mmio`] which works from a bare address.
+#[repr(transparent)]
+pub struct Volatile<T: Copy> {
+    value: UnsafeCell<T>,
+}
+
+impl<T: Copy> Volatile<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn read(&self) -> T {
+        unsafe { self.value.get().read_volatile() }
+    }
+
+    pub fn write(&self, value: T) {
+        unsafe { self.value.get().write_volatile(value) }
+    }
+
+    /// Read the value, change it with `f` and write it back.
+    ///
+    /// This is two separate accesses, not an atomic update.
+    pub fn update(&self, f: impl FnOnce(T) -> T) {
+        self.write(f(self.read()))
+    }
+}
+
+impl<T: Copy + core::fmt::Debug> core::fmt::Debug for Volatile<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Volatile").field(&self.read()).finish()
+    }
+}
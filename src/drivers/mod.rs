@@ -1,31 +1,103 @@
+pub mod console;
+pub mod keyboard;
+pub mod mmio;
+pub mod plic;
 pub mod uart_16550;
 
 use uart_16550::SerialPort;
 
-use crate::sync::spinlock::{OnceCell, SpinLock};
+use crate::sync::{once::Once, ring::RingBuffer, spinlock::SpinLock};
 
-pub static mut SERIAL: OnceCell<SpinLock<SerialPort>> = OnceCell::new();
-pub static mut UART_DONE: bool = false;
+/// Base address of the 16550 UART on the QEMU `virt` machine
+pub const UART0_BASE: u32 = 0x1000_0000;
+
+pub static SERIAL: Once<SpinLock<SerialPort>> = Once::new();
+
+/// The serial console, set up by whichever hart uses it first
+pub fn serial() -> &'static SpinLock<SerialPort> {
+    SERIAL.call_once(|| SpinLock::new(SerialPort::new(UART0_BASE)))
+}
+
+/// Bytes received by the UART interrupt handler, waiting to be read
+static RECEIVED: RingBuffer<u8, 256> = RingBuffer::new();
+
+/// Dispatch an external interrupt claimed from the PLIC
+pub fn handle_external_interrupt() {
+    while let Some(irq) = plic::claim() {
+        match irq {
+            plic::UART0_IRQ => handle_uart_interrupt(),
+            _ => crate::warn!("Unexpected external interrupt: IRQ {}", irq),
+        }
+        plic::notify_end_of_interrupt(irq);
+    }
+}
+
+fn handle_uart_interrupt() {
+    let serial = serial();
+    loop {
+        // dont hold the lock while handling the byte, as logging needs it
+        let Some(b) = serial.lock().read_byte() else {
+            break;
+        };
+        receive_byte(b);
+    }
+}
+
+/// Hand on a byte received over the UART, to [`read_byte`] and the keyboard.
+///
+/// Only the UART interrupt handler calls this outside of tests:
+/// the PLIC only lets one hart claim the interrupt at a time,
+/// so it is the only producer.
+pub fn receive_byte(b: u8) {
+    // nothing may be reading the raw bytes,
+    // so once the queue is full they are dropped
+    let _ = RECEIVED.push(b);
+    keyboard::handle_byte(b);
+}
+
+/// Take the next byte received over the UART, if there is one.
+pub fn read_byte() -> Option<u8> {
+    RECEIVED.pop()
+}
 
 #[macro_export]
 macro_rules! print {
      	($($args:tt)+) => ({
                 use core::fmt::Write;
+                // the arguments have always been evaluated in an unsafe block
+                #[allow(unused_unsafe)]
                 unsafe {
-                        if !$crate::drivers::UART_DONE && $crate::cpu::util::my_hart() != 0 {
-                                while !$crate::drivers::SERIAL.is_initialized() {
-                                core::hint::spin_loop();
-                                }
-                        }
-                        let _ = write!($crate::drivers::SERIAL.get_or_init(||
-                        $crate::sync::spinlock::SpinLock::new(
-                                $crate::drivers::uart_16550::SerialPort::new(0x1000_0000)
-                        )).lock(), $($args)+);
-                        $crate::drivers::UART_DONE = true;
+                        let _ = write!($crate::drivers::console::lock(), $($args)+);
                 }
  	});
  }
 
+/// Like `print!`, but returns a `core::fmt::Result`
+/// instead of initializing the UART if it hasn't been yet.
+#[macro_export]
+macro_rules! try_print {
+        ($($args:tt)+) => ({
+                use core::fmt::Write;
+                match $crate::drivers::SERIAL.get() {
+                        Some(serial) => write!(serial.lock(), $($args)+),
+                        None => Err(core::fmt::Error),
+                }
+        });
+}
+
+#[macro_export]
+macro_rules! try_println {
+        () => ({
+                $crate::try_print!("\r\n")
+        });
+        ($fmt:expr) => ({
+                $crate::try_print!(concat!($fmt, "\r\n"))
+        });
+        ($fmt:expr, $($args:tt)+) => ({
+                $crate::try_print!(concat!($fmt, "\r\n"), $($args)+)
+        });
+}
+
 #[macro_export]
 macro_rules! println
  {
@@ -0,0 +1,73 @@
+//! Driver for the RISC-V Platform-Level Interrupt Controller.
+//!
+//! The PLIC routes external device interrupts to the harts.
+//! Each hart has a supervisor context with its own enable bits,
+//! priority threshold and claim/complete register. An interrupt
+//! must be claimed before it is handled, and completed afterwards
+//! before the PLIC will deliver it again.
+
+use super::mmio::Mmio;
+use crate::cpu::util::my_hart;
+
+/// Base address of the PLIC on the QEMU `virt` machine
+const PLIC_BASE: usize = 0x0c00_0000;
+
+/// One priority register per interrupt source
+const PRIORITY: Mmio<u32> = Mmio::new(PLIC_BASE);
+/// One pending bit per interrupt source
+const PENDING: Mmio<u32> = Mmio::new(PLIC_BASE + 0x1000);
+
+/// IRQ number of the 16550 UART on the QEMU `virt` machine
+pub const UART0_IRQ: u32 = 10;
+
+/// Interrupts we accept, and the priority we give them
+const ENABLED_IRQS: [(u32, u32); 1] = [(UART0_IRQ, 1)];
+
+fn senable(hart: usize) -> Mmio<u32> {
+    Mmio::new(PLIC_BASE + 0x2080 + hart * 0x100)
+}
+
+fn spriority(hart: usize) -> Mmio<u32> {
+    Mmio::new(PLIC_BASE + 0x20_1000 + hart * 0x2000)
+}
+
+fn sclaim(hart: usize) -> Mmio<u32> {
+    Mmio::new(PLIC_BASE + 0x20_1004 + hart * 0x2000)
+}
+
+/// Set the priorities of the interrupts we handle,
+/// this only needs to be done once, by one hart.
+pub fn init() {
+    for (irq, priority) in ENABLED_IRQS {
+        unsafe { PRIORITY.reg(irq as usize).write(priority) }
+    }
+}
+
+/// Unmask our interrupts for this hart's supervisor context.
+pub fn enable() {
+    let hart = unsafe { my_hart() };
+    let mask = ENABLED_IRQS.iter().fold(0, |mask, (irq, _)| mask | 1 << irq);
+    unsafe {
+        senable(hart).write(mask);
+        // accept any interrupt with a non-zero priority
+        spriority(hart).write(0);
+    }
+}
+
+/// Whether `irq` is waiting to be claimed
+pub fn is_pending(irq: u32) -> bool {
+    unsafe { PENDING.reg(irq as usize / 32).read() & (1 << (irq % 32)) != 0 }
+}
+
+/// Ask the PLIC which interrupt we should handle.
+pub fn claim() -> Option<u32> {
+    match unsafe { sclaim(my_hart()).read() } {
+        0 => None,
+        irq => Some(irq),
+    }
+}
+
+/// Tell the PLIC we are done handling `irq`.
+pub fn notify_end_of_interrupt(irq: u32) {
+    unsafe { sclaim(my_hart()).write(irq) }
+}
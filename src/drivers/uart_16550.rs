@@ -1,5 +1,6 @@
 //! UART driver for the 16550 chip.
-//! Note this currently does not support reading into a buffer.
+//! Received bytes are only buffered by the interrupt handler
+//! in [`crate::drivers`], reads here poll the chip directly.
 //!
 //! # Usage Example:
 //! ```
@@ -119,6 +120,16 @@ impl SerialInner {
         unsafe { self.line_status.readb() & 0x1 == 1 }
     }
 
+    /// Read a byte from the receive buffer, if one has arrived
+    #[inline]
+    pub fn read_byte(&self) -> Option<u8> {
+        if self.read_rdy() {
+            unsafe { Some(self.data.readb()) }
+        } else {
+            None
+        }
+    }
+
     #[inline]
     fn print(&self, s: &str) {
         for b in s.bytes() {
@@ -127,6 +138,38 @@ impl SerialInner {
     }
 }
 
+/// Writes straight to the chip, bypassing every lock.
+///
+/// This is only for when the locks can't be trusted, e.g. when
+/// panicking while already printing, as output may interleave.
+pub struct RawSerial {
+    data: Port,
+    line_status: Port,
+}
+
+impl RawSerial {
+    pub fn new(base: u32) -> Self {
+        Self {
+            data: Port::new(base),
+            line_status: Port::new(base + 5),
+        }
+    }
+}
+
+impl core::fmt::Write for RawSerial {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for b in s.bytes() {
+            unsafe {
+                while self.line_status.readb() & 0x20 == 0 {
+                    core::hint::spin_loop()
+                }
+                self.data.writeb(b);
+            }
+        }
+        Ok(())
+    }
+}
+
 impl core::fmt::Write for SerialPort {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
         self.lock().print(s);
@@ -170,11 +213,11 @@ impl SerialPort {
     }
 
     pub fn read_char_non_blocking(&self) -> Option<char> {
-        let r = self.regs.lock();
-        if r.read_rdy() {
-            unsafe { Some(r.data.readb() as char) }
-        } else {
-            None
-        }
+        self.read_byte().map(|b| b as char)
+    }
+
+    /// Read a byte if one has arrived, without waiting
+    pub fn read_byte(&self) -> Option<u8> {
+        self.regs.lock().read_byte()
     }
 }
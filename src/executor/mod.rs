@@ -0,0 +1,153 @@
+//! A cooperative executor for kernel `async` tasks.
+//!
+//! Tasks run until their future returns `Pending`, and are only polled
+//! again once their [`Waker`] puts them back on the run queue, which
+//! may happen from an interrupt handler. When nothing is runnable the
+//! hart sleeps with `wfi` until the next interrupt.
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use alloc::{boxed::Box, collections::{BTreeMap, VecDeque}, sync::Arc, task::Wake};
+
+use crate::{cpu::interrupts, sync::spinlock::SpinLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+pub struct Task {
+    id: TaskId,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+impl Task {
+    pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+        Task {
+            id: TaskId::new(),
+            future: Box::pin(future),
+        }
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    fn poll(&mut self, cx: &mut Context) -> Poll<()> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Tasks waiting to be polled.
+///
+/// The lock disables interrupts while held, so wakers
+/// can safely be called from a trap handler.
+type RunQueue = Arc<SpinLock<VecDeque<TaskId>>>;
+
+struct TaskWaker {
+    id: TaskId,
+    queue: RunQueue,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.queue.lock().push_back(self.id);
+    }
+}
+
+pub struct Executor {
+    tasks: BTreeMap<TaskId, Task>,
+    queue: RunQueue,
+    waker_cache: BTreeMap<TaskId, Waker>,
+}
+
+impl Executor {
+    pub fn new() -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            queue: Arc::new(SpinLock::new(VecDeque::with_capacity(64))),
+            waker_cache: BTreeMap::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, task: Task) {
+        let id = task.id;
+        if self.tasks.insert(id, task).is_some() {
+            panic!("task {:?} spawned twice", id);
+        }
+        self.queue.lock().push_back(id);
+    }
+
+    /// Poll every task on the run queue once,
+    /// dropping those which complete.
+    pub fn run_ready_tasks(&mut self) {
+        loop {
+            // the guard must be dropped before polling,
+            // as a task may wake itself and lock the queue again
+            let next = self.queue.lock().pop_front();
+            let Some(id) = next else {
+                break;
+            };
+            // a task can be woken again after it completes
+            let Some(task) = self.tasks.get_mut(&id) else {
+                continue;
+            };
+            let waker = self.waker_cache.entry(id).or_insert_with(|| {
+                Waker::from(Arc::new(TaskWaker {
+                    id,
+                    queue: self.queue.clone(),
+                }))
+            });
+
+            let mut cx = Context::from_waker(waker);
+            if task.poll(&mut cx).is_ready() {
+                self.tasks.remove(&id);
+                self.waker_cache.remove(&id);
+            }
+        }
+    }
+
+    /// Returns `true` once every spawned task has completed
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Run tasks forever, sleeping whenever none are ready.
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.run_ready_tasks();
+            self.sleep_if_idle();
+        }
+    }
+
+    fn sleep_if_idle(&self) {
+        // an interrupt between checking the queue and `wfi` could wake a
+        // task we'd then sleep through, so check with interrupts off.
+        // `wfi` still returns once an interrupt is pending.
+        interrupts::push_off();
+        if self.queue.lock().is_empty() {
+            unsafe { core::arch::asm!("wfi") };
+        }
+        interrupts::pop_off();
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,508 @@
+//! Parsing of the Flattened Device Tree (FDT) blob.
+//!
+//! RISC-V platforms don't have ACPI, instead a devicetree describing
+//! the harts, memory and devices is handed to us at boot,
+//! with its address in `a1`.
+//!
+//! The blob is a header, followed by a structure block of big-endian
+//! tokens (nodes and their properties), and a strings block holding
+//! the property names.
+//! See the [devicetree specification](https://devicetree-specification.readthedocs.io)
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub mod topology;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const HEADER_SIZE: usize = 40;
+
+/// The newest blob version we understand
+const LAST_COMPATIBLE_VERSION: u32 = 16;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// Address of the blob we were booted with, set by `kinit`
+static FDT_ADDR: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdtError {
+    /// The header does not start with `0xd00dfeed`
+    BadMagic(u32),
+    /// The blob is not backwards compatible with version 16
+    UnsupportedVersion(u32),
+    /// A header offset, token or string points outside of the blob
+    Truncated,
+    /// An unknown token was found in the structure block
+    BadToken(u32),
+    /// Nodes are not balanced, or the structure block is not terminated
+    Malformed,
+    /// A node or property name is not valid UTF-8
+    BadString,
+    /// No blob address was handed to us at boot
+    Missing,
+}
+
+impl FdtError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BadMagic(_) => "bad FDT magic",
+            Self::UnsupportedVersion(_) => "unsupported FDT version",
+            Self::Truncated => "FDT is truncated",
+            Self::BadToken(_) => "unknown FDT structure token",
+            Self::Malformed => "malformed FDT structure block",
+            Self::BadString => "FDT string is not valid UTF-8",
+            Self::Missing => "no FDT was passed at boot",
+        }
+    }
+}
+
+impl core::fmt::Display for FdtError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadMagic(v) | Self::UnsupportedVersion(v) | Self::BadToken(v) => {
+                write!(f, "{} ({:#x})", self.as_str(), v)
+            }
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+impl core::error::Error for FdtError {}
+
+pub type FdtResult<T> = core::result::Result<T, FdtError>;
+
+/// Remember where the blob we were booted with lives
+pub fn set_address(addr: usize) {
+    FDT_ADDR.store(addr, Ordering::Relaxed);
+}
+
+/// Parse the blob we were booted with
+pub fn get() -> FdtResult<Fdt<'static>> {
+    match FDT_ADDR.load(Ordering::Relaxed) {
+        0 => Err(FdtError::Missing),
+        // Safety: the boot firmware hands us a blob that
+        // lives for as long as we do
+        addr => unsafe { Fdt::from_ptr(addr as *const u8) },
+    }
+}
+
+fn be32(data: &[u8], off: usize) -> FdtResult<u32> {
+    let bytes = data.get(off..off + 4).ok_or(FdtError::Truncated)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// Read a null terminated string from the start of `data`
+fn c_str(data: &[u8]) -> FdtResult<&str> {
+    let len = data.iter().position(|b| *b == 0).ok_or(FdtError::Truncated)?;
+    core::str::from_utf8(&data[..len]).map_err(|_| FdtError::BadString)
+}
+
+#[derive(Clone, Copy)]
+pub struct Fdt<'a> {
+    data: &'a [u8],
+    structs: &'a [u8],
+    strings: &'a [u8],
+}
+
+impl<'a> Fdt<'a> {
+    /// # Safety
+    ///
+    /// `ptr` must point to a blob which lives for `'a`.
+    pub unsafe fn from_ptr(ptr: *const u8) -> FdtResult<Self> {
+        let header = core::slice::from_raw_parts(ptr, HEADER_SIZE);
+        let magic = be32(header, 0)?;
+        if magic != FDT_MAGIC {
+            return Err(FdtError::BadMagic(magic));
+        }
+        let total_size = be32(header, 4)? as usize;
+        Self::new(core::slice::from_raw_parts(ptr, total_size))
+    }
+
+    /// Validate and parse a blob
+    pub fn new(data: &'a [u8]) -> FdtResult<Self> {
+        let magic = be32(data, 0)?;
+        if magic != FDT_MAGIC {
+            return Err(FdtError::BadMagic(magic));
+        }
+
+        let total_size = be32(data, 4)? as usize;
+        let data = data.get(..total_size).ok_or(FdtError::Truncated)?;
+
+        let last_comp_version = be32(data, 24)?;
+        if last_comp_version > LAST_COMPATIBLE_VERSION {
+            return Err(FdtError::UnsupportedVersion(last_comp_version));
+        }
+
+        let block = |off_field, size_field| -> FdtResult<&'a [u8]> {
+            let off = be32(data, off_field)? as usize;
+            let size = be32(data, size_field)? as usize;
+            data.get(off..off + size).ok_or(FdtError::Truncated)
+        };
+
+        let fdt = Self {
+            data,
+            structs: block(8, 36)?,
+            strings: block(12, 32)?,
+        };
+        fdt.validate()?;
+        Ok(fdt)
+    }
+
+    /// Walk the whole structure block, so that later
+    /// lookups can assume it is well formed.
+    fn validate(&self) -> FdtResult<()> {
+        let mut cursor = Cursor::new(self.structs, 0);
+        let mut depth = 0usize;
+
+        loop {
+            match cursor.token()? {
+                FDT_BEGIN_NODE => {
+                    cursor.name()?;
+                    depth += 1;
+                }
+                FDT_END_NODE => {
+                    depth = depth.checked_sub(1).ok_or(FdtError::Malformed)?;
+                }
+                FDT_PROP => {
+                    let (name_off, _) = cursor.prop()?;
+                    c_str(self.strings.get(name_off..).ok_or(FdtError::Truncated)?)?;
+                }
+                FDT_NOP => {}
+                FDT_END if depth == 0 => return Ok(()),
+                FDT_END => return Err(FdtError::Malformed),
+                token => return Err(FdtError::BadToken(token)),
+            }
+        }
+    }
+
+    /// Size of the whole blob, in bytes
+    pub fn total_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The ID of the hart we booted on
+    pub fn boot_hart(&self) -> u32 {
+        // the header was validated on construction
+        be32(self.data, 28).unwrap_or(0)
+    }
+
+    /// The top level node, `/`
+    pub fn root(&self) -> Option<Node<'a>> {
+        let mut cursor = Cursor::new(self.structs, 0);
+        loop {
+            match cursor.token().ok()? {
+                FDT_NOP => continue,
+                FDT_BEGIN_NODE => return Node::parse(self, cursor),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Find a node by its path, e.g. `/cpus/cpu@0`
+    ///
+    /// The unit address (after `@`) can be omitted, in
+    /// which case the first node with a matching name is found.
+    pub fn find_node(&self, path: &str) -> Option<Node<'a>> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self.root()?, |node, segment| node.child(segment))
+    }
+}
+
+/// Reads tokens out of the structure block
+#[derive(Clone, Copy)]
+struct Cursor<'a> {
+    structs: &'a [u8],
+    off: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(structs: &'a [u8], off: usize) -> Self {
+        Self { structs, off }
+    }
+
+    fn token(&mut self) -> FdtResult<u32> {
+        let token = be32(self.structs, self.off)?;
+        self.off += 4;
+        Ok(token)
+    }
+
+    fn peek(&self) -> FdtResult<u32> {
+        be32(self.structs, self.off)
+    }
+
+    /// Read the name following an `FDT_BEGIN_NODE`
+    fn name(&mut self) -> FdtResult<&'a str> {
+        let name = c_str(self.structs.get(self.off..).ok_or(FdtError::Truncated)?)?;
+        self.off = align4(self.off + name.len() + 1);
+        Ok(name)
+    }
+
+    /// Read the property following an `FDT_PROP`,
+    /// returning its name offset and value.
+    fn prop(&mut self) -> FdtResult<(usize, &'a [u8])> {
+        let len = be32(self.structs, self.off)? as usize;
+        let name_off = be32(self.structs, self.off + 4)? as usize;
+        let start = self.off + 8;
+        let value = self.structs.get(start..start + len).ok_or(FdtError::Truncated)?;
+        self.off = align4(start + len);
+        Ok((name_off, value))
+    }
+
+    /// Skip past the rest of the node we are in,
+    /// including all of its children.
+    fn skip_node(&mut self) -> FdtResult<()> {
+        let mut depth = 1usize;
+        while depth > 0 {
+            match self.token()? {
+                FDT_BEGIN_NODE => {
+                    self.name()?;
+                    depth += 1;
+                }
+                FDT_END_NODE => depth -= 1,
+                FDT_PROP => {
+                    self.prop()?;
+                }
+                FDT_NOP => {}
+                token => return Err(FdtError::BadToken(token)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+    name: &'a str,
+    /// offset of the first token after the node's name
+    body: usize,
+}
+
+impl<'a> Node<'a> {
+    /// Parse a node whose `FDT_BEGIN_NODE` the cursor has just read
+    fn parse(fdt: &Fdt<'a>, mut cursor: Cursor<'a>) -> Option<Self> {
+        let name = cursor.name().ok()?;
+        Some(Self {
+            structs: fdt.structs,
+            strings: fdt.strings,
+            name,
+            body: cursor.off,
+        })
+    }
+
+    /// The full node name, including any unit address
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Whether this node is called `name`, ignoring the
+    /// unit address if `name` doesn't specify one.
+    pub fn name_matches(&self, name: &str) -> bool {
+        self.name == name
+            || (!name.contains('@') && self.name.split('@').next() == Some(name))
+    }
+
+    pub fn properties(&self) -> Properties<'a> {
+        Properties {
+            cursor: Cursor::new(self.structs, self.body),
+            strings: self.strings,
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<Property<'a>> {
+        self.properties().find(|prop| prop.name == name)
+    }
+
+    /// Iterate over the immediate children of this node
+    pub fn children(&self) -> Children<'a> {
+        let mut cursor = Cursor::new(self.structs, self.body);
+        // properties always come before child nodes
+        while let Ok(FDT_PROP | FDT_NOP) = cursor.peek() {
+            if let Ok(FDT_PROP) = cursor.token() {
+                let _ = cursor.prop();
+            }
+        }
+        Children {
+            cursor,
+            strings: self.strings,
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<Node<'a>> {
+        self.children().find(|child| child.name_matches(name))
+    }
+
+    /// Whether any of this node's `compatible` strings are `compatible`
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.property("compatible")
+            .is_some_and(|prop| prop.strings().any(|s| s == compatible))
+    }
+
+    /// Depth-first search of this node's descendants
+    /// for one compatible with `compatible`
+    pub fn find_compatible(&self, compatible: &str) -> Option<Node<'a>> {
+        self.children().find_map(|child| {
+            if child.is_compatible(compatible) {
+                Some(child)
+            } else {
+                child.find_compatible(compatible)
+            }
+        })
+    }
+
+    /// The number of cells this node's children use for addresses
+    /// and sizes in their `reg` properties.
+    pub fn cell_sizes(&self) -> (usize, usize) {
+        // defaults as given by the specification
+        let read = |name, default| {
+            self.property(name)
+                .and_then(|prop| prop.as_u32())
+                .map_or(default, |v| v as usize)
+        };
+        (read("#address-cells", 2), read("#size-cells", 1))
+    }
+}
+
+pub struct Properties<'a> {
+    cursor: Cursor<'a>,
+    strings: &'a [u8],
+}
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = Property<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.cursor.peek().ok()? {
+                FDT_NOP => {
+                    self.cursor.off += 4;
+                }
+                FDT_PROP => {
+                    self.cursor.off += 4;
+                    let (name_off, value) = self.cursor.prop().ok()?;
+                    let name = c_str(self.strings.get(name_off..)?).ok()?;
+                    return Some(Property { name, value });
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+pub struct Children<'a> {
+    cursor: Cursor<'a>,
+    strings: &'a [u8],
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.cursor.token().ok()? {
+                FDT_NOP => continue,
+                FDT_BEGIN_NODE => {
+                    let name = self.cursor.name().ok()?;
+                    let node = Node {
+                        structs: self.cursor.structs,
+                        strings: self.strings,
+                        name,
+                        body: self.cursor.off,
+                    };
+                    self.cursor.skip_node().ok()?;
+                    return Some(node);
+                }
+                // the end of our parent node
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Property<'a> {
+    pub name: &'a str,
+    pub value: &'a [u8],
+}
+
+impl<'a> Property<'a> {
+    /// Read the `idx`th big-endian 32-bit cell
+    pub fn u32_at(&self, idx: usize) -> Option<u32> {
+        be32(self.value, idx * 4).ok()
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        self.u32_at(0)
+    }
+
+    /// Read a single value made of one or two cells
+    pub fn as_usize(&self) -> Option<usize> {
+        match self.value.len() {
+            4 => self.u32_at(0).map(|v| v as usize),
+            8 => Some((self.u32_at(0)? as usize) << 32 | self.u32_at(1)? as usize),
+            _ => None,
+        }
+    }
+
+    /// Read a null terminated string value
+    pub fn as_str(&self) -> Option<&'a str> {
+        c_str(self.value).ok()
+    }
+
+    /// Iterate over a string list value, like `compatible`
+    pub fn strings(&self) -> impl Iterator<Item = &'a str> {
+        self.value
+            .split(|b| *b == 0)
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| core::str::from_utf8(s).ok())
+    }
+
+    /// Iterate over the `(address, size)` pairs of a `reg` value,
+    /// using the cell sizes given by the parent node.
+    pub fn reg(&self, (address_cells, size_cells): (usize, usize)) -> Reg<'a> {
+        Reg {
+            value: self.value,
+            address_cells,
+            size_cells,
+            off: 0,
+        }
+    }
+}
+
+pub struct Reg<'a> {
+    value: &'a [u8],
+    address_cells: usize,
+    size_cells: usize,
+    off: usize,
+}
+
+impl Reg<'_> {
+    fn read(&mut self, cells: usize) -> Option<usize> {
+        let mut v = 0usize;
+        for _ in 0..cells {
+            v = v.checked_shl(32).unwrap_or(0) | be32(self.value, self.off).ok()? as usize;
+            self.off += 4;
+        }
+        Some(v)
+    }
+}
+
+impl Iterator for Reg<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let addr = self.read(self.address_cells)?;
+        let size = self.read(self.size_cells)?;
+        Some((addr, size))
+    }
+}
@@ -0,0 +1,98 @@
+//! The harts and interrupt controllers described by the device tree.
+
+use crate::cpu::MAX_HARTS;
+
+use super::{Fdt, FdtError, FdtResult, Node};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hart {
+    /// The hart ID, as found in `mhartid`
+    pub id: usize,
+    /// Disabled harts are present, but won't be started
+    pub enabled: bool,
+    /// Whether the hart implements the Zkr entropy source
+    pub has_zkr: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plic {
+    pub base: usize,
+    /// Number of interrupt sources
+    pub ndev: usize,
+}
+
+/// What the device tree told us about the harts and interrupt controllers.
+#[derive(Debug)]
+pub struct Topology {
+    harts: [Option<Hart>; MAX_HARTS],
+    /// Core Local Interruptor, holding the per-hart timers and software interrupts
+    pub clint: Option<usize>,
+    /// Platform-Level Interrupt Controller, routing device interrupts
+    pub plic: Option<Plic>,
+    /// Frequency of the `time` counter, in Hz
+    pub timebase_frequency: Option<usize>,
+}
+
+impl Topology {
+    pub fn from_fdt(fdt: &Fdt) -> FdtResult<Self> {
+        let mut topology = Self {
+            harts: [None; MAX_HARTS],
+            clint: None,
+            plic: None,
+            timebase_frequency: None,
+        };
+
+        let cpus = fdt.find_node("/cpus").ok_or(FdtError::Malformed)?;
+        let cells = cpus.cell_sizes();
+        topology.timebase_frequency = cpus
+            .property("timebase-frequency")
+            .and_then(|freq| freq.as_usize());
+        for cpu in cpus.children().filter(|n| n.name_matches("cpu")) {
+            let Some((id, _)) = cpu.property("reg").and_then(|reg| reg.reg(cells).next()) else {
+                continue;
+            };
+            if id >= MAX_HARTS {
+                crate::warn!("Ignoring hart {}, we only support {} harts", id, MAX_HARTS);
+                continue;
+            }
+
+            topology.harts[id] = Some(Hart {
+                id,
+                enabled: cpu
+                    .property("status")
+                    .and_then(|s| s.as_str())
+                    .is_none_or(|s| s == "okay"),
+                has_zkr: cpu
+                    .property("riscv,isa")
+                    .and_then(|s| s.as_str())
+                    .is_some_and(|isa| isa.split('_').any(|ext| ext == "zkr")),
+            });
+        }
+
+        let root = fdt.root().ok_or(FdtError::Malformed)?;
+        let soc = root.child("soc").ok_or(FdtError::Malformed)?;
+        let base = |node: Node| {
+            node.property("reg")
+                .and_then(|reg| reg.reg(soc.cell_sizes()).next())
+                .map(|(addr, _)| addr)
+        };
+
+        topology.clint = soc.find_compatible("riscv,clint0").and_then(base);
+        topology.plic = soc.find_compatible("riscv,plic0").and_then(|plic| {
+            Some(Plic {
+                base: base(plic)?,
+                ndev: plic.property("riscv,ndev")?.as_u32()? as usize,
+            })
+        });
+
+        Ok(topology)
+    }
+
+    pub fn harts(&self) -> impl Iterator<Item = &Hart> {
+        self.harts.iter().flatten()
+    }
+
+    pub fn hart_count(&self) -> usize {
+        self.harts().filter(|h| h.enabled).count()
+    }
+}
@@ -0,0 +1,55 @@
+//! What the main hart learns about the machine while booting,
+//! handed to `kernel_main` once initialization is done.
+
+use crate::{
+    fdt::{topology::Topology, Fdt, FdtResult},
+    mem,
+    sync::once::Once,
+    util::error::WalnutError,
+};
+
+pub struct BootInfo {
+    /// The device tree we were booted with
+    pub fdt: Fdt<'static>,
+    pub topology: Topology,
+    /// Base and size of the first RAM region in the device tree
+    pub memory: Option<(usize, usize)>,
+    /// Where all of physical memory is mapped
+    pub phys_offset: usize,
+}
+
+impl BootInfo {
+    pub fn from_fdt(fdt: Fdt<'static>) -> FdtResult<Self> {
+        let topology = Topology::from_fdt(&fdt)?;
+        let memory = fdt.root().and_then(|root| {
+            let cells = root.cell_sizes();
+            fdt.find_node("/memory")?.property("reg")?.reg(cells).next()
+        });
+
+        Ok(Self {
+            fdt,
+            topology,
+            memory,
+            phys_offset: mem::phys_offset(),
+        })
+    }
+}
+
+static BOOT_INFO: Once<BootInfo> = Once::new();
+
+/// Record the boot info, which the main hart does while initializing.
+///
+/// Only the first registration is kept, any later one is rejected.
+pub fn set(info: BootInfo) -> crate::Result<&'static BootInfo> {
+    let mut info = Some(info);
+    let stored = BOOT_INFO.call_once(|| info.take().unwrap());
+    match info {
+        None => Ok(stored),
+        Some(_) => Err(WalnutError::new("boot info was already set")),
+    }
+}
+
+/// The boot info, once the main hart has finished initializing.
+pub fn get() -> Option<&'static BootInfo> {
+    BOOT_INFO.get()
+}
@@ -1,23 +1,55 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+//! Leveled logging, written to the console like `print!`.
+//!
+//! To send log messages somewhere else, register an
+//! [`OutputSink`](crate::drivers::console::OutputSink).
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Log levels, from least to most severe
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Level {
-    Info,
+    Trace,
     Debug,
+    Info,
     Warn,
     Error,
 }
 
+/// Messages less severe than this are dropped
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Trace as u8);
+
+/// Only log messages at least as severe as `level`
+pub fn set_level(level: Level) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 >= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Write a message to the console, colored by level,
+/// used by the logging macros.
+pub fn log(level: Level, args: core::fmt::Arguments) {
+    if enabled(level) {
+        crate::println!("\x1B[{}m[{:^5}] {}\x1B[0m", log_color(level), level, args);
+    }
+}
+
 #[macro_export]
 macro_rules! log_level {
     ($level:expr, $($arg:tt)+) => {
         if cfg!(debug_assertions) {
-            $crate::println!(
-                "\x1B[{}m[{:^5}] {}\x1B[0m",
-                $crate::init::log::log_color($level), $level, format_args!($($arg)+)
-            );
+            $crate::init::log::log($level, format_args!($($arg)+));
         }
     }
 }
 
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => ($crate::log_level!($crate::init::log::Level::Trace, $($arg)+))
+}
+
 #[macro_export]
 macro_rules! info {
     ($($arg:tt)+) => ($crate::log_level!($crate::init::log::Level::Info, $($arg)+))
@@ -40,6 +72,7 @@ macro_rules! error {
 
 pub fn log_color(level: Level) -> &'static str {
     match level {
+        Level::Trace => "90", // Grey
         Level::Info => "32",  // Green
         Level::Debug => "36", // Cyan
         Level::Warn => "33",  // Yellow
@@ -49,11 +82,13 @@ pub fn log_color(level: Level) -> &'static str {
 
 impl core::fmt::Display for Level {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // `pad` rather than `write_str` so the `{:^5}` in `log` applies
         match self {
-            Self::Info => f.write_str("INFO"),
-            Self::Debug => f.write_str("DEBUG"),
-            Self::Warn => f.write_str("WARN"),
-            Self::Error => f.write_str("ERROR"),
+            Self::Trace => f.pad("TRACE"),
+            Self::Info => f.pad("INFO"),
+            Self::Debug => f.pad("DEBUG"),
+            Self::Warn => f.pad("WARN"),
+            Self::Error => f.pad("ERROR"),
         }
     }
 }
@@ -3,27 +3,87 @@
 
 #[macro_use]
 pub mod log;
+pub mod boot_info;
 
-use crate::cpu::{
-    csr::ControlStatusRegister, delegate_traps, mode::Mode, save_hartid, transition, util::my_hart,
+use crate::{
+    cpu::{
+        canary,
+        csr::satp::Satp,
+        delegate_traps,
+        mode::Mode,
+        percpu,
+        random, save_hartid, stack, timer, transition,
+        util::my_hart,
+    },
+    fdt,
+    util::fmt::ByteSize,
 };
 
+/// What the main hart brought up, logged once it's done
+#[derive(Debug, Clone, Copy)]
+pub struct InitReport {
+    pub heap_size: usize,
+    /// Harts described by the device tree
+    pub hart_count: usize,
+    /// Base and size of RAM, if the device tree described it
+    pub memory: Option<(usize, usize)>,
+    /// Whether the timer was calibrated from the device tree,
+    /// rather than assuming the default timebase
+    pub timer_calibrated: bool,
+    pub plic: bool,
+}
+
+impl core::fmt::Display for InitReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "heap {}, {} harts", ByteSize(self.heap_size), self.hart_count)?;
+        if let Some((base, size)) = self.memory {
+            write!(f, ", {} of RAM at {:#x}", ByteSize(size), base)?;
+        }
+        write!(
+            f,
+            ", timer {}, PLIC {}",
+            if self.timer_calibrated { "calibrated" } else { "uncalibrated" },
+            if self.plic { "found" } else { "missing" }
+        )
+    }
+}
+
 #[no_mangle]
-extern "C" fn kinit() -> ! {
+extern "C" fn kinit(hartid: usize, fdt_addr: usize) -> ! {
+    // a function on the stack as the guard changes would fail its check,
+    // so the other harts wait for the main hart to pick it
+    if hartid != 0 {
+        canary::wait();
+    }
+
     save_hartid();
-    info!("Initializing Hardware Thread {}", my_hart());
+    percpu::init_hart();
+    info!("Initializing Hardware Thread {}", unsafe { my_hart() });
+
+    // every hart is handed the same device tree
+    fdt::set_address(fdt_addr);
 
     // Disable paging (for now)
-    ControlStatusRegister::Satp.write(0);
+    Satp::new().write();
 
     delegate_traps();
 
     // configure PMP (Physical Memory Protection)
-    // so supervisor mode can access all of physical memory
-    ControlStatusRegister::Pmpaddr0.write(0x3fffffffffffff);
-    ControlStatusRegister::Pmpcfg0.write(0xf);
+    // so supervisor mode can access all of physical memory,
+    // except the guard page below this hart's stack
+    stack::protect_guard_page();
+
+    // let S-mode program its own timer interrupts
+    timer::delegate();
+
+    // and read the entropy source, if there is one
+    random::delegate();
+
+    // which is where the stack guard comes from
+    if hartid == 0 {
+        canary::init();
+    }
 
-    // TODO: ask for interrupts
     // TODO: why does xv6 keep hartid in tp reg for cpuid?
 
     unsafe { transition(Mode::Supervisor) }
@@ -0,0 +1,151 @@
+#![no_std]
+#![cfg_attr(test, no_main)]
+#![feature(asm_const, error_in_core, custom_test_frameworks, async_iterator)]
+#![test_runner(crate::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{cpu::{csr::ControlStatusRegister, interrupts, save_hartid, timer}, drivers::plic, mem::{allocator::ALLOCATOR, pages}};
+use alloc::{boxed::Box, string::String, vec::Vec};
+pub use util::Result;
+pub use init::boot_info::BootInfo;
+
+pub mod asm;
+pub mod cpu;
+pub mod drivers;
+pub mod executor;
+pub mod fdt;
+pub mod graphics;
+pub mod init;
+pub mod mem;
+pub mod power;
+pub mod process;
+pub mod shell;
+pub mod sync;
+pub mod task;
+pub mod testing;
+pub mod util;
+
+extern "C" {
+    static HEAP_SIZE: usize;
+    static HEAP_START: usize; 
+    static KERNEL_STACK_SIZE: usize;
+    static KERNEL_STACK_END: usize;
+    static KERNEL_STACK_START: usize;
+    static TEXT_START: usize;
+    static TEXT_END: usize;
+fn kernelvec();
+}
+
+// the library's own tests run from `test_main` instead
+#[cfg(not(test))]
+extern "Rust" {
+    /// Provided by the kernel binary, or by an integration test.
+    /// Called on the main hart once every hart is initialized.
+    fn kernel_main(boot_info: &'static BootInfo) -> !;
+}
+
+#[macro_use]
+extern crate alloc;
+
+#[no_mangle]
+fn kmain() {
+    main_thread_only!({
+        info!("Welcome to Walnut!");
+        match main_hart_initialization() {
+            Ok(report) => info!("Initialized: {}", report),
+            Err(e) => panic!("Error initializing OS components in main hart: {}", e),
+        }
+    });
+
+
+
+    hart_initialization();
+
+    // tests rely on traps and the timer, so run them once the hart is set up
+    main_thread_only!({
+        let online = cpu::wait_for_harts(timer::frequency() as u64);
+        info!("{} of {} harts online", online, cpu::cpu_count());
+
+        #[cfg(test)]
+        test_main();
+        #[cfg(not(test))]
+        unsafe { kernel_main(init::boot_info::get().expect("main hart initialization did not finish")) }
+    });
+
+    cpu::wfi_loop()
+}
+
+/// Bring up everything shared between harts, in order:
+///
+/// 1. the device tree, which the boot info is built from
+/// 2. the timer frequency, which the other harts' timers depend on
+/// 3. the page allocator, then the heap on top of it
+/// 4. the scheduler, which allocates the boot threads
+/// 5. the PLIC, before any hart enables external interrupts
+///
+/// This may only run once, on the main hart, before any
+/// other hart runs `hart_initialization`. `kmain` has already
+/// run it by the time `kernel_main` is called, so calling it
+/// again only returns an error.
+pub fn main_hart_initialization() -> Result<init::InitReport> {
+    static STARTED: AtomicBool = AtomicBool::new(false);
+    if STARTED.swap(true, Ordering::AcqRel) {
+        return Err(util::error::WalnutError::new("main hart initialization already ran"));
+    }
+
+    let heap_size = unsafe { HEAP_SIZE };
+    info!("We have a kernel heap size of {:#0x} ({})", heap_size, util::fmt::ByteSize(heap_size));
+
+    let fdt = fdt::get()?;
+    info!("Found a {:#0x} byte device tree, booted on hart {}", fdt.total_size(), fdt.boot_hart());
+
+    let boot_info = init::boot_info::set(BootInfo::from_fdt(fdt)?)?;
+    let topology = &boot_info.topology;
+    info!("Found {} harts, CLINT at {:#0x?}, PLIC at {:#0x?}", topology.hart_count(), topology.clint, topology.plic.map(|p| p.base));
+
+    let timer_calibrated = match topology.timebase_frequency {
+        Some(freq) => {
+            timer::calibrate(freq);
+            true
+        }
+        None => {
+            warn!("No timebase-frequency found, assuming {}Hz", timer::timebase_frequency());
+            false
+        }
+    };
+    unsafe {
+        pages::PAGE_ALLOCATOR.init();
+        ALLOCATOR.init()?;
+        // paging stays off, so the page table's permissions aren't
+        // enforced, see `mem::table::initialize`
+        //mem::table::initialize();
+    }
+    task::init();
+    plic::init();
+
+    Ok(init::InitReport {
+        heap_size: unsafe { HEAP_SIZE },
+        hart_count: topology.hart_count(),
+        memory: boot_info.memory,
+        timer_calibrated,
+        plic: topology.plic.is_some(),
+    })
+}
+
+fn hart_initialization() {
+
+    // `kernelvec` runs on the trap stack
+    cpu::stack::init_trap_stack();
+    ControlStatusRegister::Stvec.write(kernelvec as *const u8 as usize);
+    mem::table::enforce_permissions();
+
+    // only take interrupts once the trap vector is installed
+    plic::enable();
+    timer::init_hart();
+    interrupts::enable();
+
+    cpu::mark_online();
+}
@@ -1,8 +1,13 @@
 use mycelium_bitfield::bitfield;
 
+use super::pages::{Page, PageSize, Size4KiB, PAGE_SIZE};
+
 
 
 bitfield! {
+    /// Ordered and hashable, so page-aligned addresses can key the
+    /// bookkeeping of what is mapped.
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
     pub struct VirtAddr<usize> {
         pub const PAGE_OFFSET = 12;
         pub const LVL_0_IDX = 9;
@@ -12,6 +17,15 @@ bitfield! {
     }
 }
     impl VirtAddr {
+    pub const ZERO: Self = Self::from_bits(0);
+    /// The highest address, which is canonical as all of
+    /// bits 63-38 are set
+    pub const MAX: Self = Self::from_bits(usize::MAX);
+
+    pub fn is_null(&self) -> bool {
+        self.bits() == 0
+    }
+
     pub fn lvl_idx(&self, lvl: usize) -> usize {
         match lvl {
             0 => self.get(Self::LVL_0_IDX),
@@ -20,4 +34,92 @@ bitfield! {
             _ => unreachable!()
         }
     }
+
+    /// The index into each level of the table walk, from the root
+    /// (level 2) down, in the same order [`from_indices`](Self::from_indices) takes them.
+    ///
+    /// Lower levels are still reported for an address in a mega or
+    /// giga page, where they are part of the offset into the page.
+    pub fn table_indices(&self) -> (usize, usize, usize) {
+        (self.lvl_idx(2), self.lvl_idx(1), self.lvl_idx(0))
+    }
+
+    /// Build the address of the 4KiB page at the given table indices
+    pub fn from_indices(lvl2: usize, lvl1: usize, lvl0: usize) -> Self {
+        Self::new()
+            .with(Self::LVL_2_IDX, lvl2)
+            .with(Self::LVL_1_IDX, lvl1)
+            .with(Self::LVL_0_IDX, lvl0)
+            .sign_extended()
+    }
+
+    /// Build the address of the 2MiB megapage at the given table indices
+    pub fn from_mega_indices(lvl2: usize, lvl1: usize) -> Self {
+        Self::from_indices(lvl2, lvl1, 0)
+    }
+
+    /// Build the address of the 1GiB gigapage at the given table index
+    pub fn from_giga_index(lvl2: usize) -> Self {
+        Self::from_indices(lvl2, 0, 0)
+    }
+
+    /// Round down to a multiple of `align`, which must be a power of two
+    pub fn align_down(&self, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment {:#x} is not a power of two", align);
+        Self::from_bits(self.bits() & !(align - 1))
+    }
+
+    /// Round up to a multiple of `align`, which must be a power of two
+    pub fn align_up(&self, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment {:#x} is not a power of two", align);
+        let bits = self.bits().checked_add(align - 1).expect("aligning the address up overflowed");
+        Self::from_bits(bits & !(align - 1))
+    }
+
+    /// Whether this is a multiple of `align`, which must be a power of two
+    pub fn is_aligned(&self, align: usize) -> bool {
+        self.align_down(align).bits() == self.bits()
+    }
+
+    /// Whether a page of size `S` could start at this address
+    pub fn is_aligned_to_page<S: PageSize>(&self) -> bool {
+        self.is_aligned(S::SIZE)
+    }
+
+    /// The start of the `S` sized page this address falls within,
+    /// e.g. the 2MiB page containing a 4KiB one
+    pub fn align_down_to_page<S: PageSize>(&self) -> Self {
+        self.align_down(S::SIZE)
+    }
+
+    /// The start of the first `S` sized page at or after this address
+    pub fn align_up_to_page<S: PageSize>(&self) -> Self {
+        self.align_up(S::SIZE)
+    }
+
+    /// The page this address falls within.
+    ///
+    /// Debug builds check the address is canonical, as aligning
+    /// a bogus address down only gives a bogus page.
+    pub fn containing_page(&self) -> *mut Page {
+        debug_assert!(self.is_canonical(), "non-canonical address {:#x}", self.bits());
+        self.align_down(PAGE_SIZE).bits() as *mut Page
+    }
+
+    /// The page starting at this address, if it is page aligned
+    pub fn aligned_page(&self) -> Option<*mut Page> {
+        self.is_aligned_to_page::<Size4KiB>().then(|| self.containing_page())
+    }
+
+    /// Whether the address can be used at all, see [`sign_extended`](Self::sign_extended)
+    pub fn is_canonical(&self) -> bool {
+        self.sign_extended().bits() == self.bits()
+    }
+
+    /// Sv39 requires bits 63-39 to all equal bit 38,
+    /// otherwise the address faults.
+    fn sign_extended(self) -> Self {
+        const UNUSED_BITS: u32 = 64 - 39;
+        Self::from_bits((((self.bits() << UNUSED_BITS) as isize) >> UNUSED_BITS) as usize)
+    }
 }
@@ -1,7 +1,7 @@
-use core::{alloc::GlobalAlloc, cell::UnsafeCell, ptr::{null, null_mut}};
+use core::{alloc::GlobalAlloc, ptr::{null, null_mut}};
 use block::{BlockPtr, Block};
 
-use crate::{debug, error, info, mem::allocator::block::{BlockPtrMut, BLOCK_SIZE}};
+use crate::{error, mem::allocator::block::{BlockPtrMut, BLOCK_SIZE}, sync::spinlock::SpinLock};
 
 use super::pages::{PAGE_ALLOCATOR, PAGE_SIZE};
 use core::error::Error;
@@ -16,9 +16,11 @@ pub type AllocResult<T> = core::result::Result<T, AllocationError>;
 
 #[global_allocator]
 pub static mut ALLOCATOR: AllocGuard = AllocGuard { 
-    allocator: UnsafeCell::new(Allocator {
+    allocator: SpinLock::new(Allocator {
     block_cnt: 0,
-    free_list_head: null()
+    free_list_head: null(),
+    used: 0,
+    allocations: 0,
     })
 };
 
@@ -27,6 +29,28 @@ pub static mut ALLOCATOR: AllocGuard = AllocGuard {
 pub struct Allocator {
     pub block_cnt: usize,
     pub free_list_head: BlockPtr,
+    /// Bytes handed out and not yet freed, as requested by the callers
+    used: usize,
+    /// Allocations not yet freed
+    allocations: usize,
+}
+
+/// The free list is only reached through the `SpinLock` in [`AllocGuard`],
+/// so it can be handed between harts.
+unsafe impl Send for Allocator {}
+
+/// A snapshot of how the kernel heap is being used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Bytes currently allocated
+    pub used: usize,
+    /// Bytes left in the free list
+    pub free: usize,
+    /// Allocations currently live
+    pub allocations: usize,
+    /// Bytes in the biggest free block, if this is much less than
+    /// `free` the heap is fragmented
+    pub largest_free: usize,
 }
 
 impl Allocator {
@@ -69,8 +93,9 @@ impl Allocator {
     }
 
     pub fn block_alloc(&mut self, n: usize) -> AllocResult<*const u8> {
+        // nothing here may log: the console lock is taken around
+        // sinks which allocate, so this would take them in the other order
         if self.free_list_head.is_null() {
-            error!("No available blocks. Unable to allocate!");
             return Err(AllocationError::new("No non-taken blocks found to allocate with"));
         }
 
@@ -88,10 +113,11 @@ impl Allocator {
 
 
         if current.is_null() {
-            
-            debug!("No blocks big enough to hold {:#0x} found. Allocating {} pages.", n, Self::pages_for_block_cnt(n));
+            // no block big enough, fall back to whole pages
             unsafe {
-            return Ok(PAGE_ALLOCATOR.alloc(Self::pages_for_block_cnt(n)).expect("Unable to allocate pages for alloc!") as *const u8);
+            return PAGE_ALLOCATOR.alloc(Self::pages_for_block_cnt(n))
+                .map(|page| page as *const u8)
+                .ok_or(AllocationError::new("Unable to allocate pages for alloc"));
             }
         }
 
@@ -99,7 +125,7 @@ impl Allocator {
 
         if (*current).size > n {
 
-                info!("Block found was too large. Splitting into blocks of {} and {}", (*current).size - n, n);
+                // too large, split off what we don't need
                 let leftover_block = current.byte_add(n * BLOCK_SIZE) as BlockPtrMut;
                 (*leftover_block).size = (*current).size - n;
                 (*leftover_block).next = (*current).next;
@@ -150,8 +176,7 @@ impl Allocator {
             }
 
             if !prev.is_null() && (prev.byte_add((*prev).size * BLOCK_SIZE)) as BlockPtr == fb {
-
-                info!("Coalescing backwards from {:#0p} -> {:#0p}", prev, (*prev).next);
+                // coalescing backwards
             } else {
                 (*fb).next = self.free_list_head;
                 self.free_list_head = fb as BlockPtr;
@@ -163,13 +188,30 @@ impl Allocator {
                 assert!((*current).size != 0);
                 (*current).size += (*(*current).next).size;
                 (*current).next = (*(*current).next).next;
-                info!("Coalescing forwards from {:#0p} -> {:#0p}", current, (*current).next);
             }
         }
-        info!("Freed block(s) at {:0p}", ptr);
 
     }
 
+    pub fn stats(&self) -> HeapStats {
+        let (mut free, mut largest_free) = (0, 0);
+        let mut b = self.free_list_head;
+        while !b.is_null() {
+            unsafe {
+                free += (*b).size * BLOCK_SIZE;
+                largest_free = largest_free.max((*b).size * BLOCK_SIZE);
+                b = (*b).next;
+            }
+        }
+
+        HeapStats {
+            used: self.used,
+            free,
+            allocations: self.allocations,
+            largest_free,
+        }
+    }
+
     pub fn print_blocklist(&self) {
         let mut b = unsafe {Block::at_offset(self.free_list_head, 0) as BlockPtr };
             while unsafe { !(*b).next.is_null()} {
@@ -197,33 +239,66 @@ impl Allocator {
 }
 }
 
+/// The kernel heap, shared by every hart.
+///
+/// The lock also keeps interrupts off, so a thread can't be
+/// preempted half way through changing the free list.
 pub struct AllocGuard {
-    allocator: UnsafeCell<Allocator>
+    allocator: SpinLock<Allocator>
 }
 
 impl AllocGuard {
     pub fn init(&self) -> AllocResult<()> {
-        unsafe {
-            (&mut *self.allocator.get()).init()
-        }
+        self.allocator.lock().init()
     }
     pub fn alloc_cnt(&self) -> usize {
-        unsafe {
-            (&mut *self.allocator.get()).block_cnt
-        }
+        self.allocator.lock().block_cnt
+    }
+    pub fn stats(&self) -> HeapStats {
+        self.allocator.lock().stats()
     }
 }
 
 unsafe impl GlobalAlloc for AllocGuard {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        (&mut *self.allocator.get()).sub_block_alloc(layout.size()).unwrap() as *mut u8
+        let mut allocator = self.allocator.lock();
+        match allocator.sub_block_alloc(layout.size()) {
+            Ok(ptr) => {
+                allocator.used += layout.size();
+                allocator.allocations += 1;
+                ptr as *mut u8
+            }
+            Err(e) => {
+                let stats = allocator.stats();
+                // logging takes the console lock, see `block_alloc`
+                drop(allocator);
+                report_failure(layout, stats, e);
+                // `handle_alloc_error` takes it from here
+                null_mut()
+            }
+        }
     }
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: core::alloc::Layout) {
-        (&mut *self.allocator.get()).sub_block_dealloc(ptr);
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        let mut allocator = self.allocator.lock();
+        allocator.sub_block_dealloc(ptr);
+        allocator.used -= layout.size();
+        allocator.allocations -= 1;
     }
 }
 
 
+/// Explain why an allocation failed, before the kernel panics
+fn report_failure(layout: core::alloc::Layout, stats: HeapStats, e: AllocationError) {
+    error!("Failed to allocate {} bytes aligned to {}: {}", layout.size(), layout.align(), e);
+    error!(
+        "Heap has {} bytes in use over {} allocations, {} bytes free, the largest free block is {} bytes",
+        stats.used, stats.allocations, stats.free, stats.largest_free
+    );
+    if stats.free >= layout.size() {
+        error!("There is enough free memory, but it is fragmented");
+    }
+}
+
 #[derive(Debug)]
 pub struct AllocationError {
     details: &'static str,
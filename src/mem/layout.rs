@@ -0,0 +1,83 @@
+//! The layout of physical memory, as far as the kernel knows it:
+//! the RAM described by the device tree, and how the linker script
+//! carves the kernel's part of it up.
+
+use core::fmt;
+
+use crate::{println, util::fmt::ByteSize, BootInfo};
+
+use super::pages::PAGE_SIZE;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub name: &'static str,
+    pub start: usize,
+    /// One past the last byte
+    pub end: usize,
+}
+
+impl Region {
+    pub fn size(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#012x} - {:#012x} {:<14} {:>6} pages, {}",
+            self.start,
+            self.end,
+            self.name,
+            self.size().div_ceil(PAGE_SIZE),
+            ByteSize(self.size())
+        )
+    }
+}
+
+/// The regions of physical memory we know of, in address order.
+///
+/// RAM is only included if the device tree described it.
+pub fn regions(boot_info: &BootInfo) -> impl Iterator<Item = Region> {
+    let ram = boot_info.memory.map(|(base, size)| Region {
+        name: "ram",
+        start: base,
+        end: base + size,
+    });
+
+    let kernel = unsafe {
+        [
+            Region {
+                name: "kernel text",
+                start: crate::TEXT_START,
+                end: crate::TEXT_END,
+            },
+            Region {
+                name: "kernel data",
+                start: crate::TEXT_END,
+                end: crate::HEAP_START,
+            },
+            Region {
+                name: "heap",
+                start: crate::HEAP_START,
+                end: crate::HEAP_START + crate::HEAP_SIZE,
+            },
+            Region {
+                name: "kernel stacks",
+                start: crate::KERNEL_STACK_START,
+                end: crate::KERNEL_STACK_END,
+            },
+        ]
+    };
+
+    // RAM contains everything else, so it comes first
+    ram.into_iter().chain(kernel)
+}
+
+/// Print every region we know of, one per line
+pub fn print_memory_map(boot_info: &BootInfo) {
+    for region in regions(boot_info) {
+        println!("{}", region);
+    }
+}
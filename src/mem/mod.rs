@@ -1,4 +1,47 @@
 pub mod pages;
 pub mod addr;
 pub mod table;
+pub mod tlb;
 pub mod allocator;
+pub mod layout;
+pub mod vma;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Where all of physical memory is mapped in the kernel's address space.
+/// Physical memory is identity mapped, so this is 0 for now.
+static PHYS_OFFSET: AtomicUsize = AtomicUsize::new(0);
+
+pub fn phys_offset() -> usize {
+    PHYS_OFFSET.load(Ordering::Relaxed)
+}
+
+/// # Safety
+///
+/// All of physical memory must be mapped starting at `offset`.
+pub unsafe fn set_phys_offset(offset: usize) {
+    PHYS_OFFSET.store(offset, Ordering::Relaxed);
+}
+
+/// The virtual address physical address `pa` is mapped at
+pub fn phys_to_virt(pa: usize) -> usize {
+    phys_offset() + pa
+}
+
+/// Read a `T` from physical address `pa`, which need not be aligned.
+///
+/// # Safety
+///
+/// `pa` must hold a valid `T`.
+pub unsafe fn read_phys<T>(pa: usize) -> T {
+    (phys_to_virt(pa) as *const T).read_unaligned()
+}
+
+/// Write a `T` to physical address `pa`, which need not be aligned.
+///
+/// # Safety
+///
+/// Nothing else may be using the memory at `pa`.
+pub unsafe fn write_phys<T>(pa: usize, value: T) {
+    (phys_to_virt(pa) as *mut T).write_unaligned(value)
+}
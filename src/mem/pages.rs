@@ -4,6 +4,32 @@ use crate::{info, println, HEAP_START};
 
 pub const PAGE_SIZE: usize = 4096;
 
+/// One of the sizes a Sv39 leaf page can be
+pub trait PageSize {
+    const SIZE: usize;
+    /// The page table level leaves of this size are found at
+    const LEVEL: usize;
+}
+
+pub enum Size4KiB {}
+pub enum Size2MiB {}
+pub enum Size1GiB {}
+
+impl PageSize for Size4KiB {
+    const SIZE: usize = PAGE_SIZE;
+    const LEVEL: usize = 0;
+}
+
+impl PageSize for Size2MiB {
+    const SIZE: usize = PAGE_SIZE << 9;
+    const LEVEL: usize = 1;
+}
+
+impl PageSize for Size1GiB {
+    const SIZE: usize = PAGE_SIZE << 18;
+    const LEVEL: usize = 2;
+}
+
 #[repr(C, align(4096))]
 pub struct Page {
     pub data: [u8; PAGE_SIZE],
@@ -16,6 +42,21 @@ bitfield! {
     }
 }
 
+/// Hands out physical frames of size `S`, so the page table code
+/// doesn't depend on where they come from.
+pub trait FrameAllocator<S: PageSize = Size4KiB> {
+    /// The physical address of a free frame, aligned to `S::SIZE`
+    fn allocate_frame(&mut self) -> Option<usize>;
+}
+
+pub trait FrameDeallocator<S: PageSize = Size4KiB> {
+    /// # Safety
+    ///
+    /// `frame` must have come from this allocator,
+    /// and nothing may still be using it.
+    unsafe fn deallocate_frame(&mut self, frame: usize);
+}
+
 pub static mut PAGE_ALLOCATOR: PageAllocator = PageAllocator { alloc_start: 0 };
 
 pub struct PageAllocator {
@@ -64,7 +105,7 @@ impl PageAllocator {
         assert!(self.alloc_start != 0);
 
         let node = unsafe { HEAP_START } as *mut PageListNode;
-        for i in 0..page_count() - n {
+        for i in 0..page_count().saturating_sub(n) {
             if unsafe { !node_is_taken(node.add(i)) } && self.has_contig_space(node, n, i) {
                 for pg_idx in i..i+n {
                     unsafe {
@@ -114,6 +155,28 @@ impl PageAllocator {
 
 }
 
+/// A frame allocator which is always out of frames
+pub struct EmptyFrameAllocator;
+
+impl<S: PageSize> FrameAllocator<S> for EmptyFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<usize> {
+        None
+    }
+}
+
+impl FrameAllocator for PageAllocator {
+    /// Frames are zeroed
+    fn allocate_frame(&mut self) -> Option<usize> {
+        self.zalloc(1).map(|page| page as usize)
+    }
+}
+
+impl FrameDeallocator for PageAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: usize) {
+        self.dealloc(frame as *const Page);
+    }
+}
+
 fn node_is_taken(node: *mut PageListNode) -> bool {
     unsafe { (*node).get(PageListNode::TAKEN) }
 }
@@ -2,32 +2,32 @@
 //!
 //! # TODOs
 //!
-//! sfence VMA functions
 //! make satp in init fn 
 //! map fn 
-//! unmap fn 
 //! perform id mappings
 
+use core::ptr::addr_of_mut;
+
 use mycelium_bitfield::bitfield;
 
-use crate::{cpu::csr::ControlStatusRegister, info, mem::allocator::ALLOCATOR, HEAP_SIZE, HEAP_START, KERNEL_STACK_END, KERNEL_STACK_SIZE, KERNEL_STACK_START, TEXT_END, TEXT_START};
+use crate::{cpu::csr::{satp::Satp, status::SStatus, ControlStatusRegister}, info, mem::allocator::ALLOCATOR, HEAP_SIZE, HEAP_START, KERNEL_STACK_END, KERNEL_STACK_SIZE, KERNEL_STACK_START, TEXT_END, TEXT_START};
 
-use super::{addr::VirtAddr, pages::{self, PAGE_ALLOCATOR}};
+use super::{addr::VirtAddr, pages::{self, FrameAllocator, FrameDeallocator, PageSize, Size1GiB, Size2MiB, Size4KiB, PAGE_ALLOCATOR}, tlb::{self, MapperFlush}};
 
 static mut KERNEL_PAGE_TABLE: *mut PageTable = core::ptr::null_mut();
 
-fn k_pgtable() -> &'static mut PageTable {
-    unsafe {
-        &mut *KERNEL_PAGE_TABLE
-    }
-}
 
-
-#[repr(C)]
+#[repr(C, align(4096))]
 pub struct PageTable {
     entries: [PageTableEntry; 512]
 }
 
+// the MMU walks these directly, a table must fill exactly one page
+const _: () = {
+    assert!(core::mem::size_of::<PageTableEntry>() == 8);
+    assert!(core::mem::size_of::<PageTable>() == pages::PAGE_SIZE);
+};
+
 bitfield! {
     pub struct PageTableEntry<usize> {
         pub const VALID: bool;
@@ -43,10 +43,53 @@ bitfield! {
     }
 }
 
+bitfield! {
+    /// The permission and status bits of a [`PageTableEntry`]
+    pub struct PageTableFlags<usize> {
+        pub const VALID: bool;
+        pub const READ: bool;
+        pub const WRITE: bool;
+        pub const EXEC: bool;
+        pub const USER: bool;
+        pub const GLOBAL: bool;
+        pub const ACCESSED: bool;
+        pub const DIRTY: bool;
+    }
+}
+
 impl PageTableEntry {
+    const FLAGS_MASK: usize = 0xff;
+
     pub fn set_bits(&mut self, bits: usize) {
         self.0 = bits;
     }
+
+    pub fn flags(&self) -> PageTableFlags {
+        PageTableFlags::from_bits(self.0 & Self::FLAGS_MASK)
+    }
+
+    /// The physical address this entry points to,
+    /// either the next level table or the mapped page.
+    pub fn addr(&self) -> usize {
+        self.get(Self::PPN) << 12
+    }
+
+    /// Point this entry at the physical address `pa`,
+    /// replacing all of its flags with `flags`
+    pub fn set_addr(&mut self, pa: usize, flags: PageTableFlags) {
+        self.0 = 0;
+        self.set(Self::PPN, pa >> 12);
+        self.0 |= flags.bits() & Self::FLAGS_MASK;
+    }
+
+    /// A leaf entry maps a page, rather than pointing
+    /// to the next level of the table.
+    pub fn is_leaf(&self) -> bool {
+        let flags = self.flags();
+        flags.get(PageTableFlags::READ)
+            || flags.get(PageTableFlags::WRITE)
+            || flags.get(PageTableFlags::EXEC)
+    }
 }
 
 
@@ -55,6 +98,12 @@ pub fn init_hart() {
 }
 
 
+/// Build the kernel page table, with `.text` read and execute only
+/// and everything else writable but not executable, then install it.
+///
+/// Nothing calls this yet, so paging stays off and none of these
+/// permissions, W^X included, are enforced at runtime.
+///
 /// Currently this doesnt work 
 /// we did ID map the the text section and stack section
 /// All seemed fine, but i we attempt to 
@@ -63,7 +112,10 @@ pub fn init_hart() {
 pub fn initialize() {
 
     unsafe {
-        KERNEL_PAGE_TABLE = PAGE_ALLOCATOR.zalloc(1).expect("Unable to allocate page for kernel page table.") as *mut PageTable;
+        KERNEL_PAGE_TABLE = PageTableAllocator::new(&mut *addr_of_mut!(PAGE_ALLOCATOR), super::phys_offset())
+            .allocate()
+            .expect("Unable to allocate page for kernel page table.")
+            .1;
     }
 
 
@@ -76,17 +128,21 @@ pub fn initialize() {
         id_map_range(
                     TEXT_START,
                     TEXT_END,
-                    7 << 1);
+                    // read and execute
+                    5 << 1);
 
         id_map_range(
                     KERNEL_STACK_START,
                     KERNEL_STACK_END,
                     3 << 1);
 
-        map(VirtAddr::from_bits(0x1000_0000), 0x1000_0000, 3 << 1, 0);
+        identity_map(0x1000_0000, 3 << 1)
+            .expect("Unable to allocate page table for the UART")
+            .ignore();
 
     }
 
+    unsafe {
         info!("ID Mapped heap from {:#0x} to {:#0x}", 
 				HEAP_START,
                     HEAP_START + ALLOCATOR.alloc_cnt() * 4096,
@@ -99,9 +155,11 @@ pub fn initialize() {
                     TEXT_START,
                     TEXT_END, ControlStatusRegister::Sepc.read()
         );
+    }
     unsafe {
-        ControlStatusRegister::Satp.write((8 << 60) | KERNEL_PAGE_TABLE as usize >> 12);
+        Satp::sv39(KERNEL_PAGE_TABLE as usize, 0).write();
     }
+    tlb::flush_all();
 
 }
 
@@ -124,56 +182,475 @@ pub fn id_map_range(
 	// nightmares.
 	for _ in 0..num_kb_pages {
         unsafe {
-            map(VirtAddr::from_bits(memaddr as usize), memaddr as usize, bits, 0);
+            // the whole range is flushed once we are done
+            map(VirtAddr::from_bits(memaddr as usize), memaddr as usize, bits, 0)
+                .expect("Unable to allocate page table for identity mapping")
+                .ignore();
         }
         memaddr += 1 << 12;
 	}
+    tlb::flush_all();
+}
+
+
+/// Map `va` to `pa` at level `lvl` of the kernel page table,
+/// allocating any intermediate tables needed from the page allocator.
+///
+/// See [`OffsetPageTable::map_to`].
+///
+/// # Safety
+///
+/// Changing a mapping can break any code relying on the old one.
+pub unsafe fn map(va: VirtAddr, pa: usize, flags: isize, lvl: usize) -> Result<MapperFlush, MapToError> {
+    kernel_offset_table()
+        .ok_or(MapToError::NoPageTable)?
+        .map_to(va, pa, PageTableFlags::from_bits(flags as usize), lvl, &mut *addr_of_mut!(PAGE_ALLOCATOR))
+}
+
+/// See [`OffsetPageTable::identity_map`], using the kernel page table
+///
+/// # Safety
+///
+/// See [`map`].
+pub unsafe fn identity_map(pa: usize, flags: isize) -> Result<MapperFlush, MapToError> {
+    kernel_offset_table()
+        .ok_or(MapToError::NoPageTable)?
+        .identity_map(pa, PageTableFlags::from_bits(flags as usize), &mut *addr_of_mut!(PAGE_ALLOCATOR))
+}
+
+/// Back `count` pages starting at `va` with newly allocated,
+/// zeroed frames, mapped with `flags` in the kernel page table.
+///
+/// See [`OffsetPageTable::map_range`], the whole range is
+/// flushed once we are done.
+///
+/// # Safety
+///
+/// Nothing may be relying on the range being mapped to anything else.
+pub unsafe fn map_range(va: VirtAddr, count: usize, flags: isize) -> crate::Result<()> {
+    let mut table = kernel_offset_table().ok_or(MapToError::NoPageTable)?;
+    let result = table.map_range(va, count, PageTableFlags::from_bits(flags as usize), &mut *addr_of_mut!(PAGE_ALLOCATOR));
+    tlb::flush_all();
+    Ok(result?)
+}
+
+/// Unmap `count` pages starting at `va` from the kernel page table,
+/// freeing the frames that backed them.
+///
+/// See [`OffsetPageTable::unmap_range`].
+///
+/// # Safety
+///
+/// The frames must have come from the page allocator, like those
+/// mapped by [`map_range`], and nothing may still be using them.
+pub unsafe fn unmap_range(va: VirtAddr, count: usize) {
+    if let Some(mut table) = kernel_offset_table() {
+        table.unmap_range(va, count, &mut *addr_of_mut!(PAGE_ALLOCATOR));
+        tlb::flush_all();
+    }
+}
+
+
+/// Make this hart's supervisor mode accesses respect every
+/// page permission.
+///
+/// Unlike x86's `CR0.WP`, there is no switch for whether supervisor
+/// writes honour the `W` bit, they always do. What can weaken
+/// protection are `SUM`, which lets the kernel touch user pages,
+/// and `MXR`, which lets loads read execute-only pages, so
+/// both are cleared.
+pub fn enforce_permissions() {
+    SStatus::read().with(SStatus::SUM, false).with(SStatus::MXR, false).write();
+}
+
+/// Whether the kernel page table has been allocated yet
+pub fn is_initialized() -> bool {
+    !unsafe { KERNEL_PAGE_TABLE }.is_null()
+}
+
+/// Get the PTE that corresponds 
+/// to the virtual address.
+pub fn walk(va: VirtAddr) -> Option<&'static PageTableEntry> {
+    kernel_offset_table()?.walk(va)
 }
 
+/// See [`OffsetPageTable::translate`], using the kernel page table
+pub fn translate(va: VirtAddr) -> TranslateResult {
+    kernel_offset_table().map_or(TranslateResult::NotMapped, |table| table.translate(va))
+}
 
-pub unsafe fn map(va: VirtAddr, pa: usize, flags: isize, lvl: usize) {
+/// Translate a virtual address through the kernel page table
+pub fn translate_addr(va: VirtAddr) -> Option<usize> {
+    kernel_offset_table()?.translate_addr(va)
+}
 
-    assert!(flags & 0xe != 0);
+/// Remove the mapping for `va` from the kernel page table,
+/// see [`OffsetPageTable::unmap`].
+///
+/// # Safety
+///
+/// Nothing may still be using the mapping.
+pub unsafe fn unmap(va: VirtAddr) -> Option<(usize, PageTableFlags, MapperFlush)> {
+    kernel_offset_table()?.unmap(va)
+}
+
+/// The kernel page table, once it has been allocated
+pub(crate) fn kernel_offset_table() -> Option<OffsetPageTable> {
+    let root = unsafe { KERNEL_PAGE_TABLE };
+    if root.is_null() {
+        return None;
+    }
+    Some(unsafe { OffsetPageTable::new(root, super::phys_offset()) })
+}
 
-    let tbl = k_pgtable();
+/// Hands out empty page tables, in frames taken from a [`FrameAllocator`].
+///
+/// Frames must be page aligned, as the MMU requires of tables. They
+/// needn't come zeroed, each table is cleared before it's handed out
+/// so the MMU never mistakes stale data for a mapping.
+pub struct PageTableAllocator<'a, A: FrameAllocator> {
+    frames: &'a mut A,
+    phys_offset: usize,
+}
 
-    	let ppn = [
-				// PPN[0] = paddr[20:12]
-				(pa >> 12) & 0x1ff,
-				// PPN[1] = paddr[29:21]
-				(pa >> 21) & 0x1ff,
-				// PPN[2] = paddr[55:30]
-				(pa >> 30) & 0x3ff_ffff,
-	];
+impl<'a, A: FrameAllocator> PageTableAllocator<'a, A> {
+    /// Physical memory must be mapped at `phys_offset`,
+    /// so we can zero the tables.
+    pub fn new(frames: &'a mut A, phys_offset: usize) -> Self {
+        Self { frames, phys_offset }
+    }
 
-    let mut v = &mut tbl.entries[va.lvl_idx(2)];
+    /// A zeroed table, as its physical address and where we can reach it
+    pub fn allocate(&mut self) -> Option<(usize, *mut PageTable)> {
+        let frame = self.frames.allocate_frame()?;
+        assert!(frame.is_multiple_of(pages::PAGE_SIZE), "frame {:#x} can't hold a page table, it isn't page aligned", frame);
 
-    for i in (lvl..2).rev() {
+        let table = (self.phys_offset + frame) as *mut PageTable;
+        unsafe { table.write_bytes(0, 1) };
+        Some((frame, table))
+    }
+}
 
+/// Why [`OffsetPageTable::map_to`] couldn't make a mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapToError {
+    /// The allocator ran out of frames
+    FrameAllocationFailed,
+    /// A mega or giga page already covers the address, so there's
+    /// no table at the requested level to put the mapping in
+    ParentIsHugePage,
+    /// The entry is already in use, by a page or by a table of
+    /// smaller mappings
+    AlreadyMapped,
+    /// The kernel page table hasn't been allocated, see [`initialize`]
+    NoPageTable,
+    /// The flags give no permissions, which would make a table entry
+    NoPermissions,
+    /// W^X, nothing we can write to may also be executed
+    WritableAndExecutable,
+    /// Write without read is reserved
+    WriteWithoutRead,
+}
 
-        if !v.get(PageTableEntry::VALID) {
-            // TODO handle this error properly
-            let page = PAGE_ALLOCATOR.zalloc(1).unwrap();
-            v.set_bits((page as i64 >> 2) as usize);
-            v.set(PageTableEntry::VALID, true);
+impl MapToError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FrameAllocationFailed => "out of frames for page tables",
+            Self::ParentIsHugePage => "address is inside a huge page",
+            Self::AlreadyMapped => "address is already mapped",
+            Self::NoPageTable => "kernel page table is not allocated",
+            Self::NoPermissions => "mapping has no permissions",
+            Self::WritableAndExecutable => "mapping is writable and executable",
+            Self::WriteWithoutRead => "mapping is writable but not readable",
         }
-        let e = ((v.bits()as isize & !0x3ff) << 2) as *mut PageTableEntry;
-        v = e.add(va.lvl_idx(i)).as_mut().unwrap();
     }
+}
 
-    let entry = (ppn[2] << 28) as isize |   // PPN[2] = [53:28]
-			(ppn[1] << 19) as isize |   // PPN[1] = [27:19]
-			(ppn[0] << 10) as isize |   // PPN[0] = [18:10]
-			flags | 1;
-    v.set_bits(entry as usize);
+impl core::fmt::Display for MapToError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
+impl core::error::Error for MapToError {}
 
-/// Get the PTE that corresponds 
-/// to the virtual address.
-pub fn walk(va: VirtAddr) -> Option<&'static PageTableEntry> {
+/// The frame a leaf entry points to, by the size of page it maps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappedFrame {
+    Size4KiB(usize),
+    Size2MiB(usize),
+    Size1GiB(usize),
+}
+
+impl MappedFrame {
+    /// Physical address of the start of the frame
+    pub fn start(&self) -> usize {
+        match *self {
+            Self::Size4KiB(pa) | Self::Size2MiB(pa) | Self::Size1GiB(pa) => pa,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Size4KiB(_) => Size4KiB::SIZE,
+            Self::Size2MiB(_) => Size2MiB::SIZE,
+            Self::Size1GiB(_) => Size1GiB::SIZE,
+        }
+    }
+}
+
+/// What [`OffsetPageTable::translate`] found
+#[derive(Debug, Clone, Copy)]
+pub enum TranslateResult {
+    Mapped {
+        frame: MappedFrame,
+        /// Offset of the address into `frame`
+        offset: usize,
+        flags: PageTableFlags,
+    },
+    NotMapped,
+}
 
+/// A page table tree, in an address space where all of
+/// physical memory is mapped starting at `phys_offset`.
+///
+/// This lets us follow the physical addresses stored in the
+/// entries, by just adding the offset to them.
+/// While paging is disabled, or everything is identity mapped,
+/// the offset is 0.
+pub struct OffsetPageTable {
+    root: *mut PageTable,
+    phys_offset: usize,
+}
+
+impl OffsetPageTable {
+    /// # Safety
+    ///
+    /// `root` must point to a valid level 2 table, and all of physical
+    /// memory must be mapped at `phys_offset`.
+    pub unsafe fn new(root: *mut PageTable, phys_offset: usize) -> Self {
+        Self { root, phys_offset }
+    }
+
+    pub fn phys_offset(&self) -> usize {
+        self.phys_offset
+    }
+
+    fn frame_to_pointer(&self, pa: usize) -> *mut PageTable {
+        (self.phys_offset + pa) as *mut PageTable
+    }
 
+    /// Find the leaf entry mapping `va`, and the level it was found at.
+    fn leaf_entry(&self, va: VirtAddr) -> Option<(*mut PageTableEntry, usize)> {
+        let mut tbl = self.root;
+
+        for lvl in (0..=2).rev() {
+            let entry = unsafe { &mut (*tbl).entries[va.lvl_idx(lvl)] };
+            if !entry.get(PageTableEntry::VALID) {
+                return None;
+            }
+            if entry.is_leaf() {
+                return Some((entry, lvl));
+            }
+            tbl = self.frame_to_pointer(entry.addr());
+        }
+
+        // a non-leaf entry at level 0 is malformed
+        None
+    }
+
+    fn walk_with_level(&self, va: VirtAddr) -> Option<(&'static PageTableEntry, usize)> {
+        self.leaf_entry(va).map(|(entry, lvl)| (unsafe { &*entry }, lvl))
+    }
+
+    /// Get the leaf PTE that maps `va`
+    pub fn walk(&self, va: VirtAddr) -> Option<&'static PageTableEntry> {
+        self.walk_with_level(va).map(|(entry, _)| entry)
+    }
 
-    None
+    /// Find how `va` is mapped: the frame behind it, how far
+    /// into that frame it is, and the flags it is mapped with.
+    pub fn translate(&self, va: VirtAddr) -> TranslateResult {
+        let Some((entry, lvl)) = self.walk_with_level(va) else {
+            return TranslateResult::NotMapped;
+        };
+        let offset_mask = (1 << (12 + 9 * lvl)) - 1;
+        let start = entry.addr() & !offset_mask;
+        let frame = match lvl {
+            0 => MappedFrame::Size4KiB(start),
+            1 => MappedFrame::Size2MiB(start),
+            _ => MappedFrame::Size1GiB(start),
+        };
+
+        TranslateResult::Mapped {
+            frame,
+            offset: va.bits() & offset_mask,
+            // only leaves carry permissions in Sv39, so these are final
+            flags: entry.flags(),
+        }
+    }
+
+    /// Translate `va` to the physical address it is mapped to,
+    /// accounting for mega and giga pages.
+    pub fn translate_addr(&self, va: VirtAddr) -> Option<usize> {
+        match self.translate(va) {
+            TranslateResult::Mapped { frame, offset, .. } => Some(frame.start() + offset),
+            TranslateResult::NotMapped => None,
+        }
+    }
+
+    /// Map `va` to `pa` at level `lvl`, taking any intermediate
+    /// tables needed from `allocator`.
+    ///
+    /// Existing mappings are never replaced, that would leak their
+    /// frame, or for a huge page clobber the memory it maps by using
+    /// it as a table. Pages that would be both writable and executable
+    /// are refused, as are permissions Sv39 doesn't allow.
+    ///
+    /// # Safety
+    ///
+    /// Changing a mapping can break any code relying on the old one.
+    pub unsafe fn map_to(
+        &mut self,
+        va: VirtAddr,
+        pa: usize,
+        flags: PageTableFlags,
+        lvl: usize,
+        allocator: &mut impl FrameAllocator,
+    ) -> Result<MapperFlush, MapToError> {
+        let (read, write, exec) = (flags.get(PageTableFlags::READ), flags.get(PageTableFlags::WRITE), flags.get(PageTableFlags::EXEC));
+        if !(read || write || exec) {
+            return Err(MapToError::NoPermissions);
+        }
+        if write && exec {
+            return Err(MapToError::WritableAndExecutable);
+        }
+        if write && !read {
+            return Err(MapToError::WriteWithoutRead);
+        }
+
+        let mut v = &mut (*self.root).entries[va.lvl_idx(2)];
+
+        for i in (lvl..2).rev() {
+            if !v.get(PageTableEntry::VALID) {
+                let (frame, _) = PageTableAllocator::new(allocator, self.phys_offset)
+                    .allocate()
+                    .ok_or(MapToError::FrameAllocationFailed)?;
+                v.set_addr(frame, PageTableFlags::new().with(PageTableFlags::VALID, true));
+            } else if v.is_leaf() {
+                return Err(MapToError::ParentIsHugePage);
+            }
+            let tbl = self.frame_to_pointer(v.addr());
+            v = &mut (*tbl).entries[va.lvl_idx(i)];
+        }
+
+        if v.get(PageTableEntry::VALID) {
+            return Err(MapToError::AlreadyMapped);
+        }
+        v.set_addr(pa, flags.with(PageTableFlags::VALID, true));
+
+        Ok(MapperFlush::new(va))
+    }
+
+    /// Map the page at physical address `pa` to the same virtual address,
+    /// as used for device registers.
+    ///
+    /// There are no cache control bits to set, memory mapped I/O regions
+    /// are already uncached by the platform's physical memory attributes.
+    ///
+    /// # Safety
+    ///
+    /// See [`map_to`](Self::map_to).
+    pub unsafe fn identity_map(
+        &mut self,
+        pa: usize,
+        flags: PageTableFlags,
+        allocator: &mut impl FrameAllocator,
+    ) -> Result<MapperFlush, MapToError> {
+        let pa = pa & !(pages::PAGE_SIZE - 1);
+        self.map_to(VirtAddr::from_bits(pa), pa, flags, 0, allocator)
+    }
+
+    /// Back `count` pages starting at `va` with frames from `frames`,
+    /// mapped with `flags`. The frames are zeroed by the page allocator,
+    /// but not by every [`FrameAllocator`].
+    ///
+    /// If we run out of frames part way through, the pages mapped so
+    /// far are unmapped and their frames handed back. Nothing is
+    /// flushed, which is left to the caller.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may be relying on the range being mapped to anything else.
+    pub unsafe fn map_range<A: FrameAllocator + FrameDeallocator>(
+        &mut self,
+        va: VirtAddr,
+        count: usize,
+        flags: PageTableFlags,
+        frames: &mut A,
+    ) -> Result<(), MapToError> {
+        let start = va.align_down(pages::PAGE_SIZE);
+
+        for i in 0..count {
+            let page_va = VirtAddr::from_bits(start.bits() + i * pages::PAGE_SIZE);
+
+            let Some(frame) = frames.allocate_frame() else {
+                self.unmap_range(start, i, frames);
+                return Err(MapToError::FrameAllocationFailed);
+            };
+            match self.map_to(page_va, frame, flags, 0, frames) {
+                Ok(flush) => flush.ignore(),
+                Err(e) => {
+                    frames.deallocate_frame(frame);
+                    self.unmap_range(start, i, frames);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Unmap `count` pages starting at `va`, handing the
+    /// frames that backed them back to `frames`.
+    ///
+    /// Pages in the range that aren't mapped are skipped.
+    /// Intermediate tables are left in place to be reused, and
+    /// nothing is flushed, which is left to the caller.
+    ///
+    /// # Safety
+    ///
+    /// The frames must have come from `frames`,
+    /// and nothing may still be using them.
+    pub unsafe fn unmap_range(&mut self, va: VirtAddr, count: usize, frames: &mut impl FrameDeallocator) {
+        let start = va.align_down(pages::PAGE_SIZE);
+
+        for i in 0..count {
+            if let Some((pa, _, flush)) = self.unmap(VirtAddr::from_bits(start.bits() + i * pages::PAGE_SIZE)) {
+                flush.ignore();
+                frames.deallocate_frame(pa);
+            }
+        }
+    }
+
+    /// Remove the 4KiB mapping for `va`, returning the physical address
+    /// and flags it was mapped with, so the caller can free the frame.
+    ///
+    /// The whole entry is cleared, including its valid bit. Returns
+    /// `None` if `va` isn't mapped, or is mapped by a huge page, which
+    /// is left alone rather than unmapped along with all its neighbours.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may still be using the mapping.
+    pub unsafe fn unmap(&mut self, va: VirtAddr) -> Option<(usize, PageTableFlags, MapperFlush)> {
+        let (entry, lvl) = self.leaf_entry(va)?;
+        if lvl != Size4KiB::LEVEL {
+            return None;
+        }
+        let entry = &mut *entry;
+        let (pa, flags) = (entry.addr(), entry.flags());
+        entry.set_bits(0);
+
+        Some((pa, flags, MapperFlush::new(va)))
+    }
 }
@@ -0,0 +1,53 @@
+//! Flushing of cached address translations.
+//!
+//! RISC-V does not keep the TLB coherent with page table writes,
+//! so after modifying a mapping we must issue an `sfence.vma`
+//! before the new translation can be relied upon.
+
+use core::arch::asm;
+
+use super::addr::VirtAddr;
+
+/// Flush the translation for a single virtual address, in all address spaces.
+pub fn flush(va: VirtAddr) {
+    unsafe {
+        asm!("sfence.vma {}, zero", in(reg) va.bits());
+    }
+}
+
+/// Flush all translations, in all address spaces.
+pub fn flush_all() {
+    unsafe {
+        asm!("sfence.vma zero, zero");
+    }
+}
+
+/// Returned by operations that modify a mapping.
+///
+/// The caller must either [`flush`](MapperFlush::flush) the
+/// translation or explicitly [`ignore`](MapperFlush::ignore) it,
+/// e.g. when paging is not yet enabled or a full flush follows.
+#[must_use = "Page table changes must be flushed or explicitly ignored"]
+#[derive(Debug)]
+pub struct MapperFlush {
+    va: VirtAddr,
+}
+
+impl MapperFlush {
+    pub fn new(va: VirtAddr) -> Self {
+        Self { va }
+    }
+
+    /// The virtual address whose mapping was changed
+    pub fn addr(&self) -> VirtAddr {
+        self.va
+    }
+
+    /// Flush the changed translation from the TLB
+    pub fn flush(self) {
+        flush(self.va);
+    }
+
+    /// Don't flush the TLB, leaving it up to the caller.
+    pub fn ignore(self) {}
+}
@@ -0,0 +1,153 @@
+//! Bookkeeping of which parts of an address space are in use.
+//!
+//! A [`VmaSet`] only decides where regions go, mapping their pages is
+//! still up to the page table code.
+
+use alloc::collections::BTreeMap;
+
+use super::{addr::VirtAddr, pages::PAGE_SIZE, table::PageTableFlags};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmaError {
+    /// The region is empty or not page aligned
+    Unaligned,
+    /// The region is not within the set's bounds
+    OutOfBounds,
+    /// The region overlaps the named one
+    Overlaps(&'static str),
+    /// There is no gap large enough
+    NoSpace,
+}
+
+impl VmaError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unaligned => "region is empty or not page aligned",
+            Self::OutOfBounds => "region is out of bounds",
+            Self::Overlaps(_) => "region overlaps another",
+            Self::NoSpace => "no space for region",
+        }
+    }
+}
+
+impl core::fmt::Display for VmaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Overlaps(name) => write!(f, "{} ({})", self.as_str(), name),
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+impl core::error::Error for VmaError {}
+
+pub type VmaResult<T> = core::result::Result<T, VmaError>;
+
+/// A named range of virtual addresses, `start..end`
+#[derive(Debug, Clone, Copy)]
+pub struct Vma {
+    pub name: &'static str,
+    pub start: VirtAddr,
+    pub end: VirtAddr,
+    /// What the region's pages are to be mapped with
+    pub flags: PageTableFlags,
+}
+
+impl Vma {
+    pub fn size(&self) -> usize {
+        self.end.bits() - self.start.bits()
+    }
+
+    pub fn contains(&self, va: VirtAddr) -> bool {
+        self.start <= va && va < self.end
+    }
+}
+
+/// The regions of an address space between two bounds,
+/// none of which overlap.
+#[derive(Debug)]
+pub struct VmaSet {
+    /// Keyed by their start
+    areas: BTreeMap<VirtAddr, Vma>,
+    start: VirtAddr,
+    end: VirtAddr,
+}
+
+impl VmaSet {
+    /// An empty set, placing regions in `start..end`
+    pub fn new(start: VirtAddr, end: VirtAddr) -> Self {
+        assert!(start.is_aligned(PAGE_SIZE) && end.is_aligned(PAGE_SIZE), "unaligned VMA bounds");
+        assert!(start < end, "empty VMA bounds");
+        Self {
+            areas: BTreeMap::new(),
+            start,
+            end,
+        }
+    }
+
+    /// Record a region at a fixed address
+    pub fn insert(&mut self, vma: Vma) -> VmaResult<()> {
+        if vma.start >= vma.end || !vma.start.is_aligned(PAGE_SIZE) || !vma.end.is_aligned(PAGE_SIZE) {
+            return Err(VmaError::Unaligned);
+        }
+        if vma.start < self.start || vma.end > self.end {
+            return Err(VmaError::OutOfBounds);
+        }
+        // regions don't overlap, so only the last one
+        // starting before this ends can reach into it
+        if let Some((_, prev)) = self.areas.range(..vma.end).next_back() {
+            if prev.end > vma.start {
+                return Err(VmaError::Overlaps(prev.name));
+            }
+        }
+        self.areas.insert(vma.start, vma);
+        Ok(())
+    }
+
+    /// The lowest address with `size` bytes free after it,
+    /// rounded up to whole pages.
+    pub fn find_gap(&self, size: usize) -> Option<VirtAddr> {
+        let size = page_round(size)?;
+        let mut cursor = self.start.bits();
+        for vma in self.areas.values() {
+            if vma.start.bits() - cursor >= size {
+                return Some(VirtAddr::from_bits(cursor));
+            }
+            cursor = vma.end.bits();
+        }
+        (self.end.bits() - cursor >= size).then(|| VirtAddr::from_bits(cursor))
+    }
+
+    /// Place a region of `size` bytes, rounded up to whole pages,
+    /// wherever there's room, returning where it starts.
+    pub fn allocate(&mut self, name: &'static str, size: usize, flags: PageTableFlags) -> VmaResult<VirtAddr> {
+        let start = self.find_gap(size).ok_or(VmaError::NoSpace)?;
+        let end = VirtAddr::from_bits(start.bits() + page_round(size).ok_or(VmaError::NoSpace)?);
+        self.insert(Vma { name, start, end, flags })?;
+        Ok(start)
+    }
+
+    /// Forget the region starting at `start`
+    pub fn remove(&mut self, start: VirtAddr) -> Option<Vma> {
+        self.areas.remove(&start)
+    }
+
+    /// The region containing `va`
+    pub fn find(&self, va: VirtAddr) -> Option<&Vma> {
+        self.areas
+            .range(..=va)
+            .next_back()
+            .map(|(_, vma)| vma)
+            .filter(|vma| vma.contains(va))
+    }
+
+    /// Every region, lowest first
+    pub fn iter(&self) -> impl Iterator<Item = &Vma> {
+        self.areas.values()
+    }
+}
+
+/// `size` in whole pages, at least one
+fn page_round(size: usize) -> Option<usize> {
+    Some(size.checked_next_multiple_of(PAGE_SIZE)?.max(PAGE_SIZE))
+}
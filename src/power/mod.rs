@@ -0,0 +1,39 @@
+//! Powering off and resetting the machine.
+//!
+//! QEMU's `virt` machine has no ACPI, instead the SiFive test device
+//! ends the simulation with whatever status is written to it.
+
+use crate::drivers::mmio::Mmio;
+
+/// The SiFive test device on the QEMU `virt` machine
+const SIFIVE_TEST: Mmio<u32> = Mmio::new(0x10_0000);
+
+const FINISHER_FAIL: u32 = 0x3333;
+const FINISHER_PASS: u32 = 0x5555;
+const FINISHER_RESET: u32 = 0x7777;
+
+/// Power the machine off.
+pub fn shutdown() -> ! {
+    finish(FINISHER_PASS)
+}
+
+/// Reset the machine, booting it again.
+pub fn reboot() -> ! {
+    finish(FINISHER_RESET)
+}
+
+/// Power off, reporting failure with `code` to whoever ran QEMU.
+pub fn fail(code: u16) -> ! {
+    finish((code as u32) << 16 | FINISHER_FAIL)
+}
+
+fn finish(value: u32) -> ! {
+    unsafe {
+        SIFIVE_TEST.write(value);
+    }
+
+    // we only get here if there is no test device, e.g. on real
+    // hardware, so the best we can do is stop doing anything
+    crate::warn!("Unable to power off, halting instead");
+    crate::cpu::wfi_loop()
+}
@@ -0,0 +1,179 @@
+//! Parsing of ELF64 images, so we can load programs and modules.
+//!
+//! We only care about what is needed to load an image: the header,
+//! and the `PT_LOAD` program headers describing the segments to map.
+//! Section headers are ignored.
+//! See the [ELF specification](https://refspecs.linuxfoundation.org/elf/gabi4+/contents.html)
+
+use mycelium_bitfield::bitfield;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EM_RISCV: u16 = 243;
+
+const HEADER_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+const PT_LOAD: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// The image does not start with `\x7fELF`
+    BadMagic,
+    /// The image is not a 64-bit little-endian ELF
+    UnsupportedClass,
+    /// The image is not built for RISC-V
+    WrongMachine(u16),
+    /// The header or a program header points outside of the image
+    Truncated,
+    /// A segment is larger in the file than it is in memory,
+    /// or doesn't fit in the address space
+    Malformed,
+}
+
+impl ElfError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BadMagic => "bad ELF magic",
+            Self::UnsupportedClass => "ELF is not 64-bit little-endian",
+            Self::WrongMachine(_) => "ELF is not for RISC-V",
+            Self::Truncated => "ELF is truncated",
+            Self::Malformed => "malformed ELF program header",
+        }
+    }
+}
+
+impl core::fmt::Display for ElfError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::WrongMachine(v) => write!(f, "{} (machine {})", self.as_str(), v),
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+impl core::error::Error for ElfError {}
+
+pub type ElfResult<T> = core::result::Result<T, ElfError>;
+
+fn le16(data: &[u8], off: usize) -> ElfResult<u16> {
+    let bytes = data.get(off..off + 2).ok_or(ElfError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn le32(data: &[u8], off: usize) -> ElfResult<u32> {
+    let bytes = data.get(off..off + 4).ok_or(ElfError::Truncated)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn le64(data: &[u8], off: usize) -> ElfResult<usize> {
+    let bytes = data.get(off..off + 8).ok_or(ElfError::Truncated)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()) as usize)
+}
+
+bitfield! {
+    /// The `p_flags` permissions of a segment
+    pub struct SegmentFlags<u32> {
+        pub const EXEC: bool;
+        pub const WRITE: bool;
+        pub const READ: bool;
+    }
+}
+
+/// A `PT_LOAD` segment, to be mapped at `vaddr`.
+///
+/// The first `file_size` bytes come from the image, the rest of
+/// the `mem_size` bytes (the `.bss`) must be zeroed.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadSegment {
+    pub vaddr: usize,
+    pub file_offset: usize,
+    pub file_size: usize,
+    pub mem_size: usize,
+    pub flags: SegmentFlags,
+}
+
+pub struct Elf<'a> {
+    data: &'a [u8],
+    entry: usize,
+    phoff: usize,
+    phentsize: usize,
+    phnum: usize,
+}
+
+impl<'a> Elf<'a> {
+    /// Validate and parse an image, including all of its load segments
+    pub fn new(data: &'a [u8]) -> ElfResult<Self> {
+        let ident = data.get(..HEADER_SIZE).ok_or(ElfError::Truncated)?;
+        if ident[..4] != ELF_MAGIC {
+            return Err(ElfError::BadMagic);
+        }
+        if ident[4] != ELFCLASS64 || ident[5] != ELFDATA2LSB {
+            return Err(ElfError::UnsupportedClass);
+        }
+        let machine = le16(data, 18)?;
+        if machine != EM_RISCV {
+            return Err(ElfError::WrongMachine(machine));
+        }
+
+        let elf = Self {
+            data,
+            entry: le64(data, 24)?,
+            phoff: le64(data, 32)?,
+            phentsize: le16(data, 54)? as usize,
+            phnum: le16(data, 56)? as usize,
+        };
+        if elf.phentsize < PHDR_SIZE {
+            return Err(ElfError::Malformed);
+        }
+
+        // check every segment up front, so they can be iterated infallibly
+        for i in 0..elf.phnum {
+            if let Some(segment) = elf.segment(i)? {
+                let end = segment.file_offset.checked_add(segment.file_size);
+                if end.is_none_or(|end| end > data.len()) {
+                    return Err(ElfError::Truncated);
+                }
+                if segment.file_size > segment.mem_size || segment.vaddr.checked_add(segment.mem_size).is_none() {
+                    return Err(ElfError::Malformed);
+                }
+            }
+        }
+        Ok(elf)
+    }
+
+    /// The virtual address execution starts at
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// Parse the `idx`th program header, if it is a load segment
+    fn segment(&self, idx: usize) -> ElfResult<Option<LoadSegment>> {
+        let off = idx
+            .checked_mul(self.phentsize)
+            .and_then(|off| off.checked_add(self.phoff))
+            .ok_or(ElfError::Truncated)?;
+        if le32(self.data, off)? != PT_LOAD {
+            return Ok(None);
+        }
+        Ok(Some(LoadSegment {
+            flags: SegmentFlags::from_bits(le32(self.data, off + 4)?),
+            file_offset: le64(self.data, off + 8)?,
+            vaddr: le64(self.data, off + 16)?,
+            file_size: le64(self.data, off + 32)?,
+            mem_size: le64(self.data, off + 40)?,
+        }))
+    }
+
+    /// Iterate over the `PT_LOAD` segments
+    pub fn segments(&self) -> impl Iterator<Item = LoadSegment> + '_ {
+        // validated in `new`
+        (0..self.phnum).filter_map(|i| self.segment(i).ok().flatten())
+    }
+
+    /// The bytes of `segment` stored in the image
+    pub fn segment_data(&self, segment: &LoadSegment) -> &'a [u8] {
+        &self.data[segment.file_offset..segment.file_offset + segment.file_size]
+    }
+}
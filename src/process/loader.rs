@@ -0,0 +1,127 @@
+//! Loading of ELF images into the kernel page table.
+
+use core::ptr::addr_of_mut;
+
+use super::elf::{Elf, ElfError, LoadSegment, SegmentFlags};
+use crate::{
+    mem::{
+        addr::VirtAddr,
+        pages::{FrameAllocator, FrameDeallocator, PAGE_ALLOCATOR, PAGE_SIZE},
+        table::{self, OffsetPageTable, PageTableFlags},
+        tlb,
+    },
+    util::error::WalnutError,
+};
+
+/// The first page `segment` occupies, and how many pages it spans,
+/// or `None` if it runs off the end of the address space or
+/// reaches into the non-canonical hole between its two halves.
+fn segment_pages(segment: &LoadSegment) -> Option<(VirtAddr, usize)> {
+    let start = segment.vaddr & !(PAGE_SIZE - 1);
+    let end = segment.vaddr.checked_add(segment.mem_size)?.checked_next_multiple_of(PAGE_SIZE)?;
+    let (first, last) = (VirtAddr::from_bits(start), VirtAddr::from_bits(end.max(start + 1) - 1));
+    // both ends canonical and in the same half
+    let canonical = first.is_canonical() && last.is_canonical() && (start ^ last.bits()) >> 38 == 0;
+    canonical.then_some((first, (end - start) / PAGE_SIZE))
+}
+
+fn page_flags(flags: SegmentFlags) -> PageTableFlags {
+    // there is no "no execute" bit, pages are only executable with EXEC
+    PageTableFlags::new()
+        .with(PageTableFlags::READ, flags.get(SegmentFlags::READ))
+        .with(PageTableFlags::WRITE, flags.get(SegmentFlags::WRITE))
+        .with(PageTableFlags::EXEC, flags.get(SegmentFlags::EXEC))
+}
+
+/// Map and copy in a single segment, its pages must not be mapped yet.
+unsafe fn load_segment<A: FrameAllocator + FrameDeallocator>(
+    table: &mut OffsetPageTable,
+    elf: &Elf,
+    segment: &LoadSegment,
+    frames: &mut A,
+) -> crate::Result<()> {
+    let (start, count) = segment_pages(segment).ok_or(ElfError::Malformed)?;
+    let flags = segment.flags;
+    if !(flags.get(SegmentFlags::READ) || flags.get(SegmentFlags::WRITE) || flags.get(SegmentFlags::EXEC)) {
+        return Err(WalnutError::new("ELF segment has no permissions"));
+    }
+    if flags.get(SegmentFlags::WRITE) && flags.get(SegmentFlags::EXEC) {
+        return Err(WalnutError::new("ELF segment is both writable and executable"));
+    }
+    if flags.get(SegmentFlags::WRITE) && !flags.get(SegmentFlags::READ) {
+        return Err(WalnutError::new("ELF segment is writable but not readable"));
+    }
+    if (0..count).any(|i| table.walk(VirtAddr::from_bits(start.bits() + i * PAGE_SIZE)).is_some()) {
+        return Err(WalnutError::new("ELF segment overlaps an existing mapping"));
+    }
+
+    table.map_range(start, count, page_flags(flags), frames)?;
+
+    // write through the physical mapping, as paging need not be
+    // enabled, and the pages may not be writable
+    let phys = |va: usize| {
+        let pa = table.translate_addr(VirtAddr::from_bits(va)).expect("segment was just mapped");
+        (pa + table.phys_offset()) as *mut u8
+    };
+    // the frames needn't come zeroed, and the `.bss` tail must be
+    for i in 0..count {
+        core::ptr::write_bytes(phys(start.bits() + i * PAGE_SIZE), 0, PAGE_SIZE);
+    }
+    let mut data = elf.segment_data(segment);
+    let mut va = segment.vaddr;
+    while !data.is_empty() {
+        let len = data.len().min(PAGE_SIZE - va % PAGE_SIZE);
+        core::ptr::copy_nonoverlapping(data.as_ptr(), phys(va), len);
+        data = &data[len..];
+        va += len;
+    }
+    Ok(())
+}
+
+/// Map each load segment of `image` into the kernel page table,
+/// with the permissions it asks for, and return its entry point.
+///
+/// If any segment fails to load, those already loaded are unmapped.
+///
+/// # Safety
+///
+/// The image's segments must not overlap anything the kernel relies
+/// on being mapped elsewhere.
+pub unsafe fn load_elf(image: &[u8]) -> crate::Result<VirtAddr> {
+    let Some(mut table) = table::kernel_offset_table() else {
+        return Err(WalnutError::new("Kernel page table is not set up"));
+    };
+    let result = load_elf_into(&mut table, image, &mut *addr_of_mut!(PAGE_ALLOCATOR));
+    tlb::flush_all();
+    result
+}
+
+/// Like [`load_elf`], but into `table`, with pages taken from `frames`.
+/// Nothing is flushed, which is left to the caller.
+///
+/// # Safety
+///
+/// See [`load_elf`].
+pub unsafe fn load_elf_into<A: FrameAllocator + FrameDeallocator>(
+    table: &mut OffsetPageTable,
+    image: &[u8],
+    frames: &mut A,
+) -> crate::Result<VirtAddr> {
+    let elf = Elf::new(image)?;
+    let entry = VirtAddr::from_bits(elf.entry());
+    if entry.is_null() {
+        return Err(WalnutError::new("ELF image has no entry point"));
+    }
+
+    for (loaded, segment) in elf.segments().enumerate() {
+        if let Err(e) = load_segment(table, &elf, &segment, frames) {
+            for segment in elf.segments().take(loaded) {
+                // it loaded, so its pages are fine
+                let (start, count) = segment_pages(&segment).expect("loaded segment is malformed");
+                table.unmap_range(start, count, frames);
+            }
+            return Err(e);
+        }
+    }
+    Ok(entry)
+}
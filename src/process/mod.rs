@@ -1 +1,2 @@
-
+pub mod elf;
+pub mod loader;
@@ -0,0 +1,228 @@
+//! A minimal interactive shell over the serial console.
+//!
+//! Keys decoded from the UART are collected into a line, and once
+//! enter is pressed the first word picks a handler from [`COMMANDS`],
+//! which is passed the rest of the line.
+
+use core::ptr::addr_of;
+
+use crate::{
+    cpu::{self, timer},
+    drivers::keyboard::{self, DecodedKey, SpecialKey},
+    mem::{allocator::ALLOCATOR, layout, pages::PAGE_SIZE},
+    power, print, println,
+    util::{fmt::ByteSize, hexdump::hexdump},
+};
+
+const PROMPT: &str = "walnut> ";
+
+/// Longest line we accept, further input is ignored
+pub const MAX_LINE: usize = 128;
+
+pub struct Command {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub run: fn(args: &str),
+}
+
+pub static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        help: "list the available commands",
+        run: help,
+    },
+    Command {
+        name: "dump",
+        help: "<addr> [len], hexdump memory",
+        run: dump,
+    },
+    Command {
+        name: "mem",
+        help: "print the kernel heap layout",
+        run: mem,
+    },
+    Command {
+        name: "memmap",
+        help: "print the physical memory layout",
+        run: memmap,
+    },
+    Command {
+        name: "poweroff",
+        help: "power the machine off",
+        run: poweroff,
+    },
+    Command {
+        name: "reboot",
+        help: "reset the machine",
+        run: reboot,
+    },
+    Command {
+        name: "time",
+        help: "print the time since boot",
+        run: time,
+    },
+];
+
+/// A line of input being edited
+pub struct LineBuffer {
+    buf: [u8; MAX_LINE],
+    len: usize,
+}
+
+impl LineBuffer {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_LINE],
+            len: 0,
+        }
+    }
+
+    /// Apply a key to the line, echoing it back to the terminal.
+    /// Returns `true` once the line is complete.
+    pub fn feed(&mut self, key: DecodedKey) -> bool {
+        match key {
+            DecodedKey::Char(c) if c.is_ascii() && self.len < MAX_LINE => {
+                self.buf[self.len] = c as u8;
+                self.len += 1;
+                print!("{}", c);
+            }
+            DecodedKey::Special(SpecialKey::Backspace) if self.len > 0 => {
+                self.len -= 1;
+                print!("\x08 \x08");
+            }
+            DecodedKey::Special(SpecialKey::Enter) => {
+                println!();
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only ASCII is ever pushed
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Split a line into its command word and the remaining arguments
+pub fn parse(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    Some(match line.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim_start()),
+        None => (line, ""),
+    })
+}
+
+/// Run the command on `line`, returning `false` if there was no such command
+pub fn dispatch(line: &str) -> bool {
+    let Some((name, args)) = parse(line) else {
+        return true;
+    };
+    match COMMANDS.iter().find(|cmd| cmd.name == name) {
+        Some(cmd) => {
+            (cmd.run)(args);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Read and run commands forever.
+///
+/// This must only be run on one hart, as it consumes the keyboard queue.
+pub fn run() -> ! {
+    let mut line = LineBuffer::new();
+    print!("{}", PROMPT);
+
+    loop {
+        let Some(key) = keyboard::read_key() else {
+            // the next key will arrive with an interrupt
+            unsafe { core::arch::asm!("wfi") };
+            continue;
+        };
+
+        if line.feed(key) {
+            if !dispatch(line.as_str()) {
+                println!("unknown command, try `help`");
+            }
+            line.clear();
+            print!("{}", PROMPT);
+        }
+    }
+}
+
+fn help(_args: &str) {
+    for cmd in COMMANDS {
+        println!("{:<8}{}", cmd.name, cmd.help);
+    }
+}
+
+/// Parse a decimal number, or a hex one prefixed with `0x`
+pub fn parse_number(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn dump(args: &str) {
+    let mut args = args.split_whitespace().map(parse_number);
+    let (Some(Some(addr)), len) = (args.next(), args.next()) else {
+        println!("usage: dump <addr> [len]");
+        return;
+    };
+    let len = match len {
+        None => 64,
+        Some(Some(len)) => len,
+        Some(None) => {
+            println!("usage: dump <addr> [len]");
+            return;
+        }
+    };
+    // we trust whoever is at the console to give a readable address
+    unsafe { hexdump(addr, len) }
+}
+
+fn mem(_args: &str) {
+    let (start, size) = unsafe { (crate::HEAP_START, crate::HEAP_SIZE) };
+    println!("heap:   {:#x} - {:#x} ({}, {} pages)", start, start + size, ByteSize(size), size / PAGE_SIZE);
+    let stats = unsafe { (*addr_of!(ALLOCATOR)).stats() };
+    println!("kmem:   {} blocks", (*addr_of!(ALLOCATOR)).alloc_cnt());
+    println!("in use: {} in {} allocations, {} free", ByteSize(stats.used), stats.allocations, ByteSize(stats.free));
+    println!("        largest free block is {}", ByteSize(stats.largest_free));
+}
+
+fn memmap(_args: &str) {
+    match crate::init::boot_info::get() {
+        Some(boot_info) => layout::print_memory_map(boot_info),
+        None => println!("boot info is not available yet"),
+    }
+}
+
+fn poweroff(_args: &str) {
+    power::shutdown()
+}
+
+fn reboot(_args: &str) {
+    power::reboot()
+}
+
+fn time(_args: &str) {
+    let ticks = timer::uptime_ticks();
+    let hz = timer::frequency() as u64;
+    println!("up {}.{:02}s ({} ticks at {}Hz) on hart {}", ticks / hz, (ticks % hz) * 100 / hz, ticks, hz, cpu::util::my_hart());
+}
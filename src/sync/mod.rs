@@ -1 +1,3 @@
+pub mod once;
+pub mod ring;
 pub mod spinlock;
@@ -0,0 +1,97 @@
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// A value that is initialized exactly once, on first use,
+/// even if several harts race to initialize it.
+pub struct Once<T> {
+    state: AtomicU8,
+    val: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// the value is only written once, by whoever wins the race
+/// to initialize it, and only shared after that.
+unsafe impl<T> Sync for Once<T> where T: Send + Sync {}
+
+impl<T> Once<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+            val: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Get the value, initializing it with `f` if this is the first call.
+    ///
+    /// If another hart is already running its initializer we wait for it,
+    /// so `f` runs at most once. `f` must not call back into this `Once`,
+    /// or it will spin forever.
+    ///
+    /// If `f` panics the `Once` is left running, and other callers spin
+    /// forever. That is never reset, as panics abort on our target
+    /// and a panicking hart never unwinds back here.
+    pub fn call_once(&self, f: impl FnOnce() -> T) -> &T {
+        match self
+            .state
+            .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                unsafe { (*self.val.get()).write(f()) };
+                self.state.store(COMPLETE, Ordering::Release);
+            }
+            Err(_) => {
+                while self.state.load(Ordering::Acquire) != COMPLETE {
+                    core::hint::spin_loop();
+                }
+            }
+        }
+        // Safety: we only get here once the value is written
+        unsafe { (*self.val.get()).assume_init_ref() }
+    }
+
+    /// Get the value, if it has been initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_completed() {
+            Some(unsafe { (*self.val.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Drop the value, if there is one, so the next
+    /// [`call_once`](Self::call_once) initializes it again.
+    ///
+    /// # Safety
+    ///
+    /// No reference handed out by this `Once` may still be in use,
+    /// and nothing else may use it until this returns.
+    pub unsafe fn reset(&self) {
+        if self.state.swap(INCOMPLETE, Ordering::AcqRel) == COMPLETE {
+            (*self.val.get()).assume_init_drop();
+        }
+    }
+}
+
+impl<T> Default for Once<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Once<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == COMPLETE {
+            unsafe { self.val.get_mut().assume_init_drop() }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A fixed capacity, lock-free, single-producer single-consumer queue.
+///
+/// This is meant for handing data from an interrupt handler
+/// to the rest of the kernel without taking a lock in the handler.
+/// Callers must make sure only one producer and one consumer
+/// use the queue at any point in time.
+pub struct RingBuffer<T: Copy, const N: usize> {
+    buf: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// Total number of values popped
+    head: AtomicUsize,
+    /// Total number of values pushed
+    tail: AtomicUsize,
+}
+
+/// Slots are only ever accessed by one side at a time, as
+/// guarded by `head` and `tail`.
+unsafe impl<T: Copy + Send, const N: usize> Sync for RingBuffer<T, N> {}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: UnsafeCell::new([const { MaybeUninit::uninit() }; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Add a value to the back of the queue,
+    /// handing it back if the queue is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head.load(Ordering::Acquire)) == N {
+            return Err(value);
+        }
+
+        unsafe {
+            (*self.buf.get())[tail % N].write(value);
+        }
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Take the value at the front of the queue
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let value = unsafe { (*self.buf.get())[head % N].assume_init() };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Copy, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
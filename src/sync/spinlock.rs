@@ -1,8 +1,10 @@
 use core::{
     cell::UnsafeCell,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::AtomicBool,
 };
 
+use crate::cpu::interrupts;
+
 pub struct Guard<'a, T> {
     lock: &'a SpinLock<T>,
 }
@@ -30,8 +32,12 @@ impl<T> SpinLock<T> {
 
     /// Retreive a reference to the `T` value,
     /// locking the `SpinLock`
+    ///
+    /// Interrupts are disabled on this hart until the `Guard` is dropped,
+    /// otherwise a trap handler taking the same lock would deadlock.
     #[inline]
     pub fn lock(&self) -> Guard<T> {
+        interrupts::push_off();
         while self
             .locked
             .swap(true, core::sync::atomic::Ordering::Acquire)
@@ -41,6 +47,30 @@ impl<T> SpinLock<T> {
 
         Guard { lock: self }
     }
+
+    /// Like [`lock`](Self::lock), but gives up straight
+    /// away if the lock is already held.
+    #[inline]
+    pub fn try_lock(&self) -> Option<Guard<'_, T>> {
+        interrupts::push_off();
+        if self.locked.swap(true, core::sync::atomic::Ordering::Acquire) {
+            interrupts::pop_off();
+            return None;
+        }
+        Some(Guard { lock: self })
+    }
+
+    /// Take over a lock which is already held, but whose `Guard`
+    /// will never be dropped, e.g. because it lives on a stack
+    /// we have switched away from.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held, and its original `Guard` must never be used again.
+    pub unsafe fn assume_locked(&self) -> Guard<'_, T> {
+        debug_assert!(self.locked.load(core::sync::atomic::Ordering::Relaxed));
+        Guard { lock: self }
+    }
 }
 
 impl<T> core::ops::Deref for Guard<'_, T> {
@@ -72,31 +102,7 @@ impl<T> Drop for Guard<'_, T> {
         // this to create unforeseen consequences down the line
         // only creates worse and harder-to-debug errors/bugs.
         assert!(prev_val);
-    }
-}
-
-pub struct OnceCell<T> {
-    initialized: AtomicBool,
-    value: Option<T>,
-}
-
-impl<T> OnceCell<T> {
-    pub const fn new() -> Self {
-        OnceCell {
-            initialized: AtomicBool::new(false),
-            value: None,
-        }
-    }
-
-    pub fn get_or_init(&mut self, f: impl FnOnce() -> T) -> &mut T {
-        if !self.initialized.load(Ordering::Relaxed) {
-            self.value = Some(f());
-            self.initialized.store(true, Ordering::Relaxed);
-        }
-        self.value.as_mut().unwrap()
-    }
 
-    pub fn is_initialized(&self) -> bool {
-        self.initialized.load(Ordering::Relaxed) && self.value.is_some()
+        interrupts::pop_off();
     }
 }
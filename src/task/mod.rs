@@ -0,0 +1,260 @@
+//! Preemptive, round-robin scheduling of kernel threads.
+//!
+//! Every hart starts out running its boot thread, on the boot stack.
+//! [`spawn`] adds threads to a shared ready queue, and on each timer
+//! tick the running thread is put at the back of the queue and the
+//! hart switches to the one at the front, on whichever hart.
+//!
+//! Each thread also has its own trap stack. A thread preempted from
+//! the timer interrupt is switched away from inside `handle_trap`,
+//! with its trap frame still on its trap stack, so that frame can't
+//! be shared with the next thread to take a trap.
+//!
+//! The scheduler lock is held across [`switch_context`], and released
+//! by the thread being switched to, as in xv6. That way no other hart
+//! can pick up the old thread before its registers are saved.
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{boxed::Box, collections::VecDeque, vec, vec::Vec};
+
+use crate::{
+    cpu::{csr::ControlStatusRegister, percpu::this_hart, stack, util::my_hart, MAX_HARTS},
+    sync::{
+        once::Once,
+        spinlock::{Guard, SpinLock},
+    },
+};
+
+/// Size of each spawned thread's kernel stack
+pub const THREAD_STACK_SIZE: usize = 4096 * 4;
+
+/// The registers preserved across a call, which is all
+/// [`switch_context`] needs to save.
+///
+/// The layout must match `switch.s`.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct Context {
+    ra: usize,
+    sp: usize,
+    s: [usize; 12],
+}
+
+// a reordering here would silently break `switch_context`
+const _: () = {
+    assert!(core::mem::offset_of!(Context, ra) == 0);
+    assert!(core::mem::offset_of!(Context, sp) == 8);
+    assert!(core::mem::offset_of!(Context, s) == 16);
+    assert!(core::mem::size_of::<Context>() == 112);
+};
+
+extern "C" {
+    fn switch_context(old: *mut Context, new: *const Context);
+    fn thread_trampoline();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadId(usize);
+
+impl ThreadId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+pub struct Thread {
+    id: ThreadId,
+    context: Context,
+    /// Where `sscratch` points while this thread runs
+    trap_stack_top: usize,
+    /// Set once the thread has finished, so the next switch
+    /// away from it frees it rather than requeueing it
+    exited: bool,
+    /// The kernel stack followed by the trap stack,
+    /// `None` for boot threads which run on the boot stack
+    stacks: Option<Box<[u8]>>,
+}
+
+impl Thread {
+    fn new(entry: fn()) -> Box<Self> {
+        let stacks = vec![0u8; THREAD_STACK_SIZE + stack::TRAP_STACK_SIZE].into_boxed_slice();
+        let base = stacks.as_ptr() as usize;
+        // the stack pointer must stay 16 byte aligned
+        let stack_top = (base + THREAD_STACK_SIZE) & !0xf;
+        let trap_stack_top = (base + stacks.len()) & !0xf;
+
+        let mut context = Context {
+            ra: thread_trampoline as *const u8 as usize,
+            sp: stack_top,
+            ..Context::default()
+        };
+        // s0 is the frame pointer, so leave it zeroed to end backtraces
+        context.s[1] = entry as usize;
+
+        Box::new(Self {
+            id: ThreadId::new(),
+            context,
+            trap_stack_top,
+            exited: false,
+            stacks: Some(stacks),
+        })
+    }
+
+    /// The thread already running on `hart`, whose
+    /// context is only filled in once it's switched away from
+    fn boot(hart: usize) -> Box<Self> {
+        Box::new(Self {
+            id: ThreadId::new(),
+            context: Context::default(),
+            trap_stack_top: stack::trap_stack_top(hart),
+            exited: false,
+            stacks: None,
+        })
+    }
+
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    /// Bounds of the thread's stacks, empty for a boot thread
+    fn stack_range(&self) -> Range<usize> {
+        match &self.stacks {
+            Some(stacks) => {
+                let range = stacks.as_ptr_range();
+                range.start as usize..range.end as usize
+            }
+            None => 0..0,
+        }
+    }
+}
+
+struct Scheduler {
+    ready: VecDeque<Box<Thread>>,
+    running: [Option<Box<Thread>>; MAX_HARTS],
+    /// Threads which have exited, freed once we're off their stack.
+    /// These stay boxed, `switch` saves into the context after queueing.
+    #[allow(clippy::vec_box)]
+    dead: Vec<Box<Thread>>,
+}
+
+static SCHEDULER: Once<SpinLock<Scheduler>> = Once::new();
+
+/// Set up the scheduler, with a boot thread for each hart.
+///
+/// This must be called once the heap is initialized,
+/// before any thread is spawned.
+pub fn init() {
+    SCHEDULER.call_once(|| {
+        SpinLock::new(Scheduler {
+            ready: VecDeque::with_capacity(16),
+            running: core::array::from_fn(|hart| Some(Thread::boot(hart))),
+            dead: Vec::new(),
+        })
+    });
+}
+
+fn scheduler() -> &'static SpinLock<Scheduler> {
+    SCHEDULER.get().expect("scheduler used before task::init")
+}
+
+/// Start running `entry` on a new thread
+pub fn spawn(entry: fn()) -> ThreadId {
+    let thread = Thread::new(entry);
+    let id = thread.id;
+    scheduler().lock().ready.push_back(thread);
+    id
+}
+
+/// Give up the hart to the next ready thread, if there is one
+pub fn yield_now() {
+    switch(scheduler().lock());
+}
+
+/// Called on every timer interrupt, to switch threads.
+pub(crate) fn preempt() {
+    let Some(scheduler) = SCHEDULER.get() else {
+        return;
+    };
+    // the thread we switch to may take a trap of its own,
+    // clobbering the CSRs `sret` needs to get back to this one
+    let sepc = ControlStatusRegister::Sepc.read();
+    let sstatus = ControlStatusRegister::SStatus.read();
+
+    switch(scheduler.lock());
+
+    ControlStatusRegister::Sepc.write(sepc);
+    ControlStatusRegister::SStatus.write(sstatus);
+}
+
+/// End the running thread.
+fn exit() -> ! {
+    let mut scheduler = scheduler().lock();
+    // with the lock held we can't be moved to another hart
+    let hart = unsafe { my_hart() };
+    scheduler.running[hart].as_mut().expect("hart has no running thread").exited = true;
+    drop(scheduler);
+
+    // idle until we're switched away from, by this or by preemption
+    loop {
+        yield_now();
+        unsafe { core::arch::asm!("wfi") };
+    }
+}
+
+/// Switch to the thread at the front of the ready queue, returning
+/// once this thread is switched back to. The running thread is
+/// requeued, unless it has exited, and is left running if no other
+/// thread is ready.
+fn switch(mut scheduler: Guard<Scheduler>) {
+    let hart = unsafe { my_hart() };
+    // otherwise we'd hand another thread our interrupt state
+    assert_eq!(this_hart().off_depth.load(Ordering::Relaxed), 1, "switching threads while holding a lock");
+
+    let Some(next) = scheduler.ready.pop_front() else {
+        return;
+    };
+    let next_context = &next.context as *const Context;
+    let next_trap_stack = next.trap_stack_top;
+    let next_stack = next.stack_range();
+    let mut prev = scheduler.running[hart].replace(next).expect("hart has no running thread");
+    // the box keeps the context in place as the thread is moved between queues
+    let prev_context = &mut prev.context as *mut Context;
+    if prev.exited {
+        scheduler.dead.push(prev);
+    } else {
+        scheduler.ready.push_back(prev);
+    }
+
+    // whether to re-enable interrupts is per thread, not per hart
+    let were_enabled = this_hart().were_enabled.load(Ordering::Relaxed);
+    ControlStatusRegister::Sscratch.write(next_trap_stack);
+    // for backtraces, see `util::backtrace`
+    this_hart().stack_start.store(next_stack.start, Ordering::Relaxed);
+    this_hart().stack_end.store(next_stack.end, Ordering::Relaxed);
+    unsafe { switch_context(prev_context, next_context) };
+
+    // we've been switched back to, maybe on another hart,
+    // with the lock held by the thread we switched from
+    this_hart().were_enabled.store(were_enabled, Ordering::Relaxed);
+    scheduler.dead.clear();
+}
+
+/// Where new threads start, once `thread_trampoline` has
+/// moved their entry point into place.
+#[no_mangle]
+#[allow(improper_ctypes_definitions)] // only called from our assembly
+extern "C" fn thread_start(entry: fn()) -> ! {
+    // we were switched to with the scheduler locked, see `switch`
+    let mut scheduler = unsafe { scheduler().assume_locked() };
+    this_hart().were_enabled.store(true, Ordering::Relaxed);
+    scheduler.dead.clear();
+    drop(scheduler);
+
+    entry();
+    exit()
+}
@@ -0,0 +1,253 @@
+//! Support for running kernel tests under QEMU.
+//!
+//! Tests are collected by the `custom_test_frameworks` feature and run
+//! on the main hart once it is initialized. The result is reported by
+//! exiting QEMU through the SiFive test device (see [`power`]), so
+//! `cargo test` sees a non-zero exit status on failure.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use alloc::vec::Vec;
+
+use crate::{
+    cpu::timer,
+    mem::pages::{FrameAllocator, FrameDeallocator},
+    power, print, println,
+    sync::once::Once,
+};
+
+/// How long a single test may run before it is considered hung,
+/// in timer ticks (5 seconds at the default tick rate).
+pub const TEST_TIMEOUT_TICKS: u64 = 5 * timer::DEFAULT_TICK_HZ as u64;
+
+/// Tick at which the running test times out, 0 if no test is running
+static DEADLINE: AtomicU64 = AtomicU64::new(0);
+
+/// Checks the failure of a test expected to fail, see [`expect_failure`]
+static FAILURE_CHECK: Once<fn() -> bool> = Once::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum QemuExitCode {
+    Success = 0,
+    Failed = 1,
+}
+
+/// Exit QEMU with the given status code.
+pub fn exit_qemu(code: QemuExitCode) -> ! {
+    match code {
+        QemuExitCode::Success => power::shutdown(),
+        code => power::fail(code as u16),
+    }
+}
+
+pub trait Testable {
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn run(&self) {
+        print!("{}...\t", core::any::type_name::<T>());
+        DEADLINE.store(timer::uptime_ticks() + TEST_TIMEOUT_TICKS, Ordering::Relaxed);
+        self();
+        DEADLINE.store(0, Ordering::Relaxed);
+        if FAILURE_CHECK.is_completed() {
+            println!("[passed, but expected to fail]\n");
+            exit_qemu(QemuExitCode::Failed);
+        }
+        println!("[ok]");
+    }
+}
+
+pub fn test_runner(tests: &[&dyn Testable]) {
+    println!("Running {} tests", tests.len());
+    for test in tests {
+        test.run();
+    }
+    exit_qemu(QemuExitCode::Success);
+}
+
+/// Expect the running test to fail, so the test harness itself can be
+/// tested. When it does, `check` is called and QEMU exits successfully
+/// if it returns `true`. If the test passes, QEMU exits with a failure.
+///
+/// Either way QEMU exits, so this must be the last test in its binary.
+pub fn expect_failure(check: fn() -> bool) {
+    FAILURE_CHECK.call_once(|| check);
+}
+
+/// Exit QEMU for the failure of the running test, which has
+/// already been reported
+fn fail_test() -> ! {
+    if let Some(check) = FAILURE_CHECK.get() {
+        if check() {
+            println!("[failed as expected]");
+            exit_qemu(QemuExitCode::Success);
+        }
+        println!("[failed, but not as expected]");
+    }
+    exit_qemu(QemuExitCode::Failed);
+}
+
+/// Give the running test `ticks` from now to finish, rather
+/// than the usual [`TEST_TIMEOUT_TICKS`]
+pub fn set_timeout(ticks: u64) {
+    DEADLINE.store(timer::uptime_ticks() + ticks, Ordering::Relaxed);
+}
+
+/// Fail the running test if it has overrun its deadline,
+/// called on every timer tick.
+///
+/// This relies on the test leaving interrupts enabled.
+pub fn check_watchdog() {
+    let deadline = DEADLINE.load(Ordering::Relaxed);
+    if deadline != 0 && timer::uptime_ticks() >= deadline {
+        println!("[timed out]\n");
+        fail_test();
+    }
+}
+
+/// Report a failed test and exit QEMU, used by the test-mode panic handler.
+pub fn test_panic_handler(info: &core::panic::PanicInfo) -> ! {
+    println!("[failed]\n");
+    println!("Error: {}\n", info);
+    fail_test();
+}
+
+/// What a failed [`kassert!`](crate::kassert) reports
+pub struct AssertionFailure<'a> {
+    /// The condition, as written
+    pub expr: &'static str,
+    /// The sides of a failed `kassert_eq!` or `kassert_ne!`
+    pub operands: Option<(&'a dyn core::fmt::Debug, &'a dyn core::fmt::Debug)>,
+    pub message: Option<core::fmt::Arguments<'a>>,
+    pub file: &'static str,
+    pub line: u32,
+}
+
+impl core::fmt::Display for AssertionFailure<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "assertion failed: `{}` at {}:{}", self.expr, self.file, self.line)?;
+        if let Some((left, right)) = self.operands {
+            write!(f, "\n  left: {:?}\n right: {:?}", left, right)?;
+        }
+        if let Some(message) = self.message {
+            write!(f, "\n{}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Report a failed assertion in a test and exit QEMU
+#[doc(hidden)]
+pub fn kassert_failed(failure: &AssertionFailure) -> ! {
+    println!("[failed]\n");
+    println!("{}\n", failure);
+    fail_test();
+}
+
+/// Like `assert!`, but under `cfg(test)` a failure is reported with
+/// the expression and where it is, and QEMU exits straight away.
+/// Otherwise a failure panics with the same report.
+#[macro_export]
+macro_rules! kassert {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            $crate::__kassert_fail!(stringify!($cond), None, None);
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::__kassert_fail!(stringify!($cond), None, Some(format_args!($($arg)+)));
+        }
+    };
+}
+
+/// Like `assert_eq!`, reporting failures as [`kassert!`] does
+#[macro_export]
+macro_rules! kassert_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__kassert_cmp!(==, $left, $right, None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__kassert_cmp!(==, $left, $right, Some(format_args!($($arg)+)))
+    };
+}
+
+/// Like `assert_ne!`, reporting failures as [`kassert!`] does
+#[macro_export]
+macro_rules! kassert_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__kassert_cmp!(!=, $left, $right, None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__kassert_cmp!(!=, $left, $right, Some(format_args!($($arg)+)))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __kassert_cmp {
+    ($op:tt, $left:expr, $right:expr, $message:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    $crate::__kassert_fail!(
+                        concat!(stringify!($left), " ", stringify!($op), " ", stringify!($right)),
+                        Some((left as &dyn core::fmt::Debug, right as &dyn core::fmt::Debug)),
+                        $message
+                    );
+                }
+            }
+        }
+    };
+}
+
+// `cfg(test)` is checked in the crate using the macro, not in walnut
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __kassert_fail {
+    ($expr:expr, $operands:expr, $message:expr) => {{
+        let failure = $crate::testing::AssertionFailure {
+            expr: $expr,
+            operands: $operands,
+            message: $message,
+            file: file!(),
+            line: line!(),
+        };
+        #[cfg(test)]
+        $crate::testing::kassert_failed(&failure);
+        #[cfg(not(test))]
+        panic!("{}", failure);
+    }};
+}
+
+/// Hands out a fixed set of frames, so tests can
+/// control exactly when the mapper runs out.
+pub struct VecFrameAllocator {
+    frames: Vec<usize>,
+}
+
+impl VecFrameAllocator {
+    /// Frames are handed out from the back of `frames`
+    pub fn new(frames: Vec<usize>) -> Self {
+        Self { frames }
+    }
+
+    /// Frames not handed out yet
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl FrameAllocator for VecFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<usize> {
+        self.frames.pop()
+    }
+}
+
+impl FrameDeallocator for VecFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: usize) {
+        self.frames.push(frame);
+    }
+}
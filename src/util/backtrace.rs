@@ -0,0 +1,106 @@
+//! Stack unwinding by following frame pointers.
+//!
+//! This relies on the kernel being built with `-Cforce-frame-pointers=yes`
+//! (see `.cargo/config.toml`). Every frame then stores the return address
+//! at `fp - 8` and the caller's frame pointer at `fp - 16`.
+//!
+//! Frame pointers are only followed while they stay on a stack we know
+//! of: a hart's boot stack, a trap stack, or the running thread's
+//! stacks. A trap's frames lead from its trap stack back onto the stack
+//! it interrupted, so the walk may move between stacks, but on any one
+//! stack callers' frames must always be above ours.
+
+use core::{arch::asm, fmt::Write, ops::Range};
+
+use crate::{
+    cpu::{percpu::this_hart, stack, MAX_HARTS},
+    drivers::console,
+};
+
+/// Stop unwinding after this many frames, in case of a loop
+const MAX_FRAMES: usize = 64;
+
+/// Print the return addresses of each frame on the stack,
+/// returning how many frames were printed.
+pub fn print_backtrace() -> usize {
+    write_backtrace(&mut console::lock())
+}
+
+/// Like [`print_backtrace`], but to `w`
+pub fn write_backtrace(w: &mut impl Write) -> usize {
+    let fp: usize;
+    unsafe {
+        asm!("mv {}, s0", out(reg) fp);
+    }
+
+    let _ = write!(w, "Backtrace:\r\n");
+    let mut depth = 0;
+    for ra in unsafe { Frames::new(fp, find_stack) } {
+        let _ = write!(w, "  #{:<2} {:#018x}\r\n", depth, ra);
+        depth += 1;
+    }
+    depth
+}
+
+/// The stack `addr` is on, counting a frame pointer at
+/// the very top as on it, as it is for the outermost frame
+pub fn find_stack(addr: usize) -> Option<Range<usize>> {
+    let boot = (0..MAX_HARTS).map(|hart| stack::stack_top(hart) - stack::STACK_SIZE..stack::stack_top(hart));
+    let trap = (0..MAX_HARTS).map(|hart| stack::trap_stack_top(hart) - stack::TRAP_STACK_SIZE..stack::trap_stack_top(hart));
+    this_hart()
+        .thread_stack()
+        .into_iter()
+        .chain(boot)
+        .chain(trap)
+        .find(|stack| stack.start < addr && addr <= stack.end)
+}
+
+/// The return addresses of a chain of frames, innermost first
+pub struct Frames<F> {
+    fp: usize,
+    stack: Option<Range<usize>>,
+    find_stack: F,
+    depth: usize,
+}
+
+impl<F: Fn(usize) -> Option<Range<usize>>> Frames<F> {
+    /// Walk the frames from `fp`, using `find_stack` to tell which
+    /// stack, if any, a frame pointer is on.
+    ///
+    /// # Safety
+    ///
+    /// Every stack `find_stack` returns must be readable.
+    pub unsafe fn new(fp: usize, find_stack: F) -> Self {
+        Self {
+            fp,
+            stack: find_stack(fp),
+            find_stack,
+            depth: 0,
+        }
+    }
+}
+
+impl<F: Fn(usize) -> Option<Range<usize>>> Iterator for Frames<F> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let stack = self.stack.as_ref()?;
+        // both words of the frame record must be on the stack
+        if self.depth >= MAX_FRAMES || !self.fp.is_multiple_of(8) || self.fp < stack.start + 16 || self.fp > stack.end {
+            return None;
+        }
+        let (ra, prev_fp) = unsafe { (*((self.fp - 8) as *const usize), *((self.fp - 16) as *const usize)) };
+        if ra == 0 {
+            return None;
+        }
+        self.depth += 1;
+
+        // the stack grows down, so callers' frames are always above ours
+        self.stack = match (self.find_stack)(prev_fp) {
+            Some(next) if next == *stack && prev_fp <= self.fp => None,
+            next => next,
+        };
+        self.fp = prev_fp;
+        Some(ra)
+    }
+}
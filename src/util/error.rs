@@ -1,38 +1,80 @@
 use core::error::Error;
 
-use crate::mem::allocator::AllocationError;
+use crate::{fdt::FdtError, mem::{allocator::AllocationError, table::MapToError, vma::VmaError}, process::elf::ElfError};
 
 
 
+/// The kernel-wide error type, wrapping the errors of each subsystem
+/// so initialization code spanning several of them can use `?`.
 #[derive(Debug)]
-pub struct WalnutError {
-    details: &'static str,
+pub enum WalnutError {
+    Allocation(AllocationError),
+    Fdt(FdtError),
+    Elf(ElfError),
+    Map(MapToError),
+    Vma(VmaError),
+    Other(&'static str),
 }
 
 impl WalnutError {
     pub fn new(msg: &'static str) -> WalnutError {
-        WalnutError { details: msg }
+        WalnutError::Other(msg)
     }
 }
 
 impl core::fmt::Display for WalnutError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", self.details)
+        match self {
+            Self::Allocation(e) => write!(f, "Allocation Error: {}", e),
+            Self::Fdt(e) => write!(f, "Device Tree Error: {}", e),
+            Self::Elf(e) => write!(f, "ELF Error: {}", e),
+            Self::Map(e) => write!(f, "Mapping Error: {}", e),
+            Self::Vma(e) => write!(f, "VMA Error: {}", e),
+            Self::Other(msg) => write!(f, "{}", msg),
+        }
     }
 }
 
 impl Error for WalnutError {
-    fn description(&self) -> &str {
-        self.details
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Allocation(e) => Some(e),
+            Self::Fdt(e) => Some(e),
+            Self::Elf(e) => Some(e),
+            Self::Map(e) => Some(e),
+            Self::Vma(e) => Some(e),
+            Self::Other(_) => None,
+        }
     }
 }
 
 
 impl From<AllocationError> for WalnutError {
     fn from(value: AllocationError) -> Self {
-        // this is likely violating the geneva convention
-        // TODO Make this cleaner!
-        Self::new(format_args!("Allocation Error: {}", value.description()).as_str().unwrap())
+        Self::Allocation(value)
     }
 }
 
+impl From<FdtError> for WalnutError {
+    fn from(value: FdtError) -> Self {
+        Self::Fdt(value)
+    }
+}
+
+impl From<ElfError> for WalnutError {
+    fn from(value: ElfError) -> Self {
+        Self::Elf(value)
+    }
+}
+
+impl From<MapToError> for WalnutError {
+    fn from(value: MapToError) -> Self {
+        Self::Map(value)
+    }
+}
+
+impl From<VmaError> for WalnutError {
+    fn from(value: VmaError) -> Self {
+        Self::Vma(value)
+    }
+}
@@ -0,0 +1,33 @@
+//! Helpers for printing values in a human readable way.
+
+use core::fmt;
+
+/// A number of bytes, displayed in the largest binary unit
+/// it fills, to one decimal place, e.g. `3.5 MiB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub usize);
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        let bytes = self.0 as u128;
+        if bytes < 1024 {
+            return write!(f, "{} B", bytes);
+        }
+
+        let mut unit = 1024;
+        let mut idx = 0;
+        while idx + 1 < UNITS.len() && bytes >= unit * 1024 {
+            unit *= 1024;
+            idx += 1;
+        }
+
+        // rounded down, so we never overstate how much there is
+        let tenths = bytes * 10 / unit;
+        match tenths % 10 {
+            0 => write!(f, "{} {}", tenths / 10, UNITS[idx]),
+            frac => write!(f, "{}.{} {}", tenths / 10, frac, UNITS[idx]),
+        }
+    }
+}
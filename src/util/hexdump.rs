@@ -0,0 +1,70 @@
+//! Dumping memory over serial, as hex and ASCII.
+
+use core::fmt;
+
+use crate::drivers::console;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// A line of the dump, covering the 16 byte aligned
+/// block starting at `addr`.
+struct Line<'a> {
+    addr: usize,
+    /// Column of the first byte, for unaligned starts
+    first: usize,
+    bytes: &'a [u8],
+}
+
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}  ", self.addr)?;
+        for col in 0..BYTES_PER_LINE {
+            match col.checked_sub(self.first).and_then(|i| self.bytes.get(i)) {
+                Some(b) => write!(f, "{:02x} ", b)?,
+                None => f.write_str("   ")?,
+            }
+            if col == BYTES_PER_LINE / 2 - 1 {
+                f.write_str(" ")?;
+            }
+        }
+
+        f.write_str(" |")?;
+        for _ in 0..self.first {
+            f.write_str(" ")?;
+        }
+        for b in self.bytes {
+            let c = if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' };
+            write!(f, "{}", c)?;
+        }
+        f.write_str("|")
+    }
+}
+
+/// Print `data` as a hexdump, labelled with the addresses it lives at.
+pub fn hexdump_bytes(data: &[u8]) {
+    let _ = write_hexdump(&mut console::lock(), data.as_ptr() as usize, data);
+}
+
+/// Write `data` to `w` as a hexdump, labelled as if it started at `start`
+pub fn write_hexdump(w: &mut impl fmt::Write, start: usize, data: &[u8]) -> fmt::Result {
+    let mut addr = start & !(BYTES_PER_LINE - 1);
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        let first = start.saturating_sub(addr);
+        let (bytes, next) = rest.split_at(rest.len().min(BYTES_PER_LINE - first));
+        write!(w, "{}\r\n", Line { addr, first, bytes })?;
+        rest = next;
+        addr += BYTES_PER_LINE;
+    }
+    Ok(())
+}
+
+/// Print the `len` bytes at `addr` as a hexdump.
+///
+/// # Safety
+///
+/// All of `addr..addr + len` must be readable.
+pub unsafe fn hexdump(addr: usize, len: usize) {
+    hexdump_bytes(core::slice::from_raw_parts(addr as *const u8, len));
+}
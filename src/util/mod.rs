@@ -1,5 +1,9 @@
-mod panic;
+pub mod panic;
+pub mod backtrace;
 pub mod error;
+pub mod fmt;
+pub mod hexdump;
+pub mod rand;
 
 pub type Result<T> = core::result::Result<T, error::WalnutError>;
 
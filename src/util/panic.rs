@@ -1,10 +1,52 @@
-use core::panic::PanicInfo;
+use core::{
+    fmt::Write,
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use crate::{cpu::util::my_hart, println};
+use crate::{
+    cpu::{util::my_hart, wfi_loop, MAX_HARTS},
+    drivers::{console, uart_16550::RawSerial, UART0_BASE},
+};
 
+/// How many times to try for the console before writing to the UART
+/// directly, long enough for another hart to finish printing a line.
+const CONSOLE_TRIES: usize = 100_000;
+
+/// Harts which are reporting a panic
+static PANICKING: [AtomicBool; MAX_HARTS] = [const { AtomicBool::new(false) }; MAX_HARTS];
+
+/// Report a panic and park the hart.
+///
+/// The report goes to every output sink, unless the console stays
+/// locked, e.g. because this hart panicked while printing, in which
+/// case it goes straight to the UART. A panic while reporting a panic
+/// only gets its message written to the UART.
+///
+/// The `#[panic_handler]` itself lives in each binary, so the kernel
+/// and the integration tests can handle panics differently.
+pub fn kernel_panic(info: &PanicInfo) -> ! {
+    let hart = unsafe { my_hart() };
+    if PANICKING[hart].swap(true, Ordering::AcqRel) {
+        let _ = write!(RawSerial::new(UART0_BASE), "\r\nPANIC WHILE PANICKING IN HART#{}: {}\r\n", hart, info.message());
+        wfi_loop()
+    }
+
+    match (0..CONSOLE_TRIES).find_map(|_| console::try_lock()) {
+        Some(mut console) => report(&mut console, hart, info),
+        None => report(&mut RawSerial::new(UART0_BASE), hart, info),
+    }
+
+    wfi_loop()
+}
+
+fn report(w: &mut impl Write, hart: usize, info: &PanicInfo) {
+    let _ = write!(w, "PANIC IN HART#{}!!!\r\n {:#x?}\r\n", hart, info);
+    super::backtrace::write_backtrace(w);
+}
+
+#[cfg(test)]
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
-    println!("PANIC IN HART#{}!!!\n {:#x?}", my_hart(), info);
-
-    loop {}
+    crate::testing::test_panic_handler(info)
 }
@@ -0,0 +1,54 @@
+//! A small, seedable pseudo-random number generator.
+//!
+//! This is xorshift64*, which is fast and good enough for stress tests
+//! and spreading things out, but it is predictable, so it must never
+//! be used for anything security sensitive.
+
+/// Seed used when there's no reason to pick another,
+/// so runs are reproducible.
+pub const DEFAULT_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Two generators with the same seed produce the same sequence
+    pub const fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero
+        Self {
+            state: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+
+    /// Seeded from the hardware entropy source, falling
+    /// back to [`DEFAULT_SEED`] if this hart has none.
+    ///
+    /// The sequence is still predictable from its first outputs.
+    pub fn from_hardware() -> Self {
+        Self::new(crate::cpu::random::hardware_u64().unwrap_or(DEFAULT_SEED))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `lo..hi`, which must not be empty
+    pub fn next_range(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo < hi, "empty range {}..{}", lo, hi);
+        // scale into the range by taking the top of a 128 bit product,
+        // which is uniform enough for our purposes
+        let span = (hi - lo) as u128;
+        lo + ((self.next_u64() as u128 * span) >> 64) as u64
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
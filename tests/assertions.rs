@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use walnut::{kassert, kassert_eq, kassert_ne, testing::AssertionFailure, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_kassert_reports_operands() {
+    kassert!(1 < 2);
+    kassert_eq!(1 + 1, 2, "maths is broken");
+    kassert_ne!(1, 2);
+
+    struct Buf([u8; 128], usize);
+    impl Write for Buf {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let end = self.1 + s.len();
+            self.0.get_mut(self.1..end).ok_or(core::fmt::Error)?.copy_from_slice(s.as_bytes());
+            self.1 = end;
+            Ok(())
+        }
+    }
+
+    // a failure exits QEMU, so check what `kassert_eq!(a, b)` would report
+    let (a, b) = (3, 4);
+    let failure = AssertionFailure {
+        expr: "a == b",
+        operands: Some((&a, &b)),
+        message: None,
+        file: "tests/assertions.rs",
+        line: 1,
+    };
+    let mut buf = Buf([0; 128], 0);
+    write!(buf, "{}", failure).unwrap();
+    assert_eq!(
+        core::str::from_utf8(&buf.0[..buf.1]).unwrap(),
+        "assertion failed: `a == b` at tests/assertions.rs:1\n  left: 3\n right: 4"
+    );
+}
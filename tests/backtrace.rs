@@ -0,0 +1,130 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{
+    ops::Range,
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{vec, vec::Vec};
+use walnut::{
+    task,
+    util::backtrace::{write_backtrace, Frames},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Throws the backtrace away, we only look at its depth
+struct Discard;
+
+impl core::fmt::Write for Discard {
+    fn write_str(&mut self, _s: &str) -> core::fmt::Result {
+        Ok(())
+    }
+}
+
+/// A fake stack, with frame records built in by hand
+struct FakeStack(Vec<usize>);
+
+impl FakeStack {
+    fn new() -> Self {
+        Self(vec![0; 32])
+    }
+
+    fn range(&self) -> Range<usize> {
+        let range = self.0.as_ptr_range();
+        range.start as usize..range.end as usize
+    }
+
+    /// The frame pointer of a frame whose record ends at word `i`
+    fn fp(&self, i: usize) -> usize {
+        self.range().start + i * 8
+    }
+
+    fn push_frame(&mut self, i: usize, ra: usize, prev_fp: usize) -> usize {
+        self.0[i - 2] = prev_fp;
+        self.0[i - 1] = ra;
+        self.fp(i)
+    }
+}
+
+fn walk(fp: usize, stacks: &[&FakeStack]) -> Vec<usize> {
+    let find = |addr: usize| {
+        stacks
+            .iter()
+            .map(|stack| stack.range())
+            .find(|stack| stack.start < addr && addr <= stack.end)
+    };
+    unsafe { Frames::new(fp, find) }.collect()
+}
+
+#[test_case]
+fn test_frames_follow_chain() {
+    let mut stack = FakeStack::new();
+    let outer = stack.push_frame(20, 0x300, 0);
+    let middle = stack.push_frame(10, 0x200, outer);
+    let inner = stack.push_frame(4, 0x100, middle);
+    assert_eq!(walk(inner, &[&stack]), [0x100, 0x200, 0x300]);
+}
+
+#[test_case]
+fn test_frames_stop_going_down_the_stack() {
+    let mut stack = FakeStack::new();
+    let inner_fp = stack.fp(4);
+    let outer = stack.push_frame(20, 0x300, inner_fp);
+    let middle = stack.push_frame(10, 0x200, outer);
+    let inner = stack.push_frame(4, 0x100, middle);
+    // the outer frame points back at the inner one, which isn't a caller
+    assert_eq!(walk(inner, &[&stack]), [0x100, 0x200, 0x300]);
+}
+
+#[test_case]
+fn test_frames_stop_off_the_stack() {
+    let mut stack = FakeStack::new();
+    let inner = stack.push_frame(4, 0x100, 0x8000_0000);
+    assert_eq!(walk(inner, &[&stack]), [0x100]);
+    assert_eq!(walk(0x8000_0000, &[&stack]), []);
+}
+
+#[test_case]
+fn test_frames_cross_stacks() {
+    // a trap's frames lead back to the stack it interrupted,
+    // wherever that is compared to the trap stack
+    let (mut interrupted, mut trap) = (FakeStack::new(), FakeStack::new());
+    let outer = interrupted.push_frame(20, 0x300, 0);
+    let middle = interrupted.push_frame(10, 0x200, outer);
+    let inner = trap.push_frame(4, 0x100, middle);
+    assert_eq!(walk(inner, &[&interrupted, &trap]), [0x100, 0x200, 0x300]);
+}
+
+static THREAD_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+#[test_case]
+fn test_backtrace_on_thread_stack() {
+    // thread stacks are on the heap, away from the boot stacks
+    task::spawn(|| THREAD_DEPTH.store(write_backtrace(&mut Discard), Ordering::Relaxed));
+    while THREAD_DEPTH.load(Ordering::Relaxed) == 0 {
+        task::yield_now();
+    }
+}
+
+#[test_case]
+fn test_backtrace_on_boot_stack() {
+    assert!(write_backtrace(&mut Discard) > 0);
+}
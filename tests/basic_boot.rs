@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{println, util::error::WalnutError, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_println() {
+    println!("test_println output");
+}
+
+#[test_case]
+fn test_main_hart_initialization_runs_once() {
+    let again = walnut::main_hart_initialization();
+    assert!(matches!(again, Err(WalnutError::Other("main hart initialization already ran"))));
+}
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{fdt::topology::Topology, init::boot_info, mem, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+fn boot_info() -> &'static BootInfo {
+    boot_info::get().expect("boot info was not set while booting")
+}
+
+#[test_case]
+fn test_boot_info_matches_device_tree() {
+    let info = boot_info();
+    let root = info.fdt.root().unwrap();
+    let memory = info.fdt.find_node("/memory").unwrap().property("reg").unwrap().reg(root.cell_sizes()).next();
+    assert!(memory.is_some());
+    assert_eq!(info.memory, memory);
+
+    let topology = Topology::from_fdt(&info.fdt).unwrap();
+    assert_eq!(info.topology.hart_count(), topology.hart_count());
+    assert_eq!(info.topology.clint, topology.clint);
+    assert_eq!(info.phys_offset, mem::phys_offset());
+}
+
+#[test_case]
+fn test_second_registration_rejected() {
+    let info = boot_info();
+    let again = BootInfo::from_fdt(info.fdt).unwrap();
+    assert!(boot_info::set(again).is_err());
+    // the first one is still what everyone sees
+    assert!(core::ptr::eq(boot_info(), info));
+}
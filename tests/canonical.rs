@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    mem::addr::VirtAddr,
+    println,
+    testing::{exit_qemu, QemuExitCode},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+/// The last test is expected to end up here
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    if alloc::format!("{}", info.message()).starts_with("non-canonical address") {
+        println!("[ok]");
+        exit_qemu(QemuExitCode::Success);
+    }
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Bit 38 is clear, but bit 39 isn't
+const NON_CANONICAL: usize = 1 << 39;
+
+#[test_case]
+fn test_is_canonical() {
+    assert!(VirtAddr::ZERO.is_canonical());
+    assert!(VirtAddr::MAX.is_canonical());
+    assert!(VirtAddr::from_bits(0x3f_ffff_ffff).is_canonical());
+    assert!(!VirtAddr::from_bits(NON_CANONICAL).is_canonical());
+    assert!(!VirtAddr::from_bits(0x40_0000_0000).is_canonical());
+}
+
+#[test_case]
+fn test_containing_page_rejects_non_canonical() {
+    VirtAddr::from_bits(NON_CANONICAL).containing_page();
+    panic!("no assertion for a non-canonical address");
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    drivers::console::{self, OutputSink},
+    print,
+    sync::spinlock::SpinLock,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_output_sinks() {
+    struct Capture(SpinLock<([u8; 32], usize)>);
+
+    impl OutputSink for Capture {
+        fn write_str(&self, s: &str) {
+            let mut guard = self.0.lock();
+            let (buf, len) = &mut *guard;
+            let n = s.len().min(buf.len() - *len);
+            buf[*len..*len + n].copy_from_slice(&s.as_bytes()[..n]);
+            *len += n;
+        }
+    }
+
+    impl Capture {
+        fn contents(&self) -> ([u8; 32], usize) {
+            *self.0.lock()
+        }
+    }
+
+    static FIRST: Capture = Capture(SpinLock::new(([0; 32], 0)));
+    static SECOND: Capture = Capture(SpinLock::new(([0; 32], 0)));
+
+    console::register(&FIRST).unwrap();
+    console::register(&SECOND).unwrap();
+    print!("to {} sinks ", 2);
+    assert!(console::unregister(&FIRST));
+    assert!(console::unregister(&SECOND));
+    assert!(!console::unregister(&SECOND));
+
+    for sink in [&FIRST, &SECOND] {
+        let (buf, len) = sink.contents();
+        assert_eq!(&buf[..len], b"to 2 sinks ");
+    }
+}
+
+#[test_case]
+fn test_console_try_lock() {
+    // the panic handler falls back to the raw UART in this case
+    let held = console::lock();
+    assert!(console::try_lock().is_none());
+    drop(held);
+    assert!(console::try_lock().is_some());
+}
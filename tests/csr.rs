@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    cpu::csr::{satp::Satp, status::SStatus},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+// none of these touch the CSRs themselves, only the values
+
+#[test_case]
+fn test_satp_layout() {
+    let satp = Satp::sv39(0x8020_0000, 5);
+    // MODE in bits 63-60, ASID in 59-44, PPN in 43-0
+    assert_eq!(satp.bits(), 8 << 60 | 5 << 44 | 0x80200);
+    assert_eq!(satp.get(Satp::MODE), Satp::MODE_SV39);
+    assert_eq!(satp.get(Satp::ASID), 5);
+    assert_eq!(satp.root_table(), 0x8020_0000);
+    assert!(satp.paging_enabled());
+}
+
+#[test_case]
+fn test_satp_round_trip() {
+    // the widest PPN there is, with every other field clear
+    let ppn = (1 << 44) - 1;
+    let satp = Satp::from_bits(ppn);
+    assert_eq!(satp.get(Satp::PPN), ppn);
+    assert_eq!(satp.get(Satp::ASID), 0);
+    assert!(!satp.paging_enabled());
+    assert_eq!(Satp::new().with(Satp::PPN, ppn).bits(), ppn);
+
+    let bits = Satp::sv39(0x8765_4000, 0xffff).bits();
+    assert_eq!(Satp::from_bits(bits).bits(), bits);
+    assert_eq!(Satp::from_bits(bits).root_table(), 0x8765_4000);
+}
+
+#[test_case]
+fn test_sstatus_layout() {
+    let bit = |field| SStatus::new().with(field, true).bits();
+    assert_eq!(bit(SStatus::SIE), 1 << 1);
+    assert_eq!(bit(SStatus::SPIE), 1 << 5);
+    assert_eq!(bit(SStatus::SPP), 1 << 8);
+    assert_eq!(bit(SStatus::SUM), 1 << 18);
+    assert_eq!(bit(SStatus::MXR), 1 << 19);
+    assert_eq!(bit(SStatus::SD), 1 << 63);
+    assert_eq!(SStatus::new().with(SStatus::FS, 0b11).bits(), 0b11 << 13);
+    assert_eq!(SStatus::new().with(SStatus::UXL, 2).bits(), 2 << 32);
+}
+
+#[test_case]
+fn test_sstatus_round_trip() {
+    let bits = 1 << 63 | 2 << 32 | 1 << 19 | 0b01 << 13 | 1 << 8 | 1 << 1;
+    let sstatus = SStatus::from_bits(bits);
+    assert!(sstatus.get(SStatus::SIE) && sstatus.get(SStatus::SPP) && sstatus.get(SStatus::MXR) && sstatus.get(SStatus::SD));
+    assert!(!sstatus.get(SStatus::SPIE) && !sstatus.get(SStatus::SUM));
+    assert_eq!(sstatus.get(SStatus::FS), 0b01);
+    assert_eq!(sstatus.get(SStatus::UXL), 2);
+    assert_eq!(sstatus.with(SStatus::SUM, false).with(SStatus::MXR, false).bits(), bits & !(1 << 19));
+}
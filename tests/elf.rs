@@ -0,0 +1,252 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{panic::PanicInfo, ptr::addr_of_mut};
+
+use alloc::vec::Vec;
+use walnut::{
+    mem::{
+        addr::VirtAddr,
+        pages::{Page, PAGE_SIZE},
+        table::{OffsetPageTable, PageTable, PageTableFlags},
+    },
+    process::{
+        elf::{Elf, ElfError, SegmentFlags},
+        loader::load_elf_into,
+    },
+    testing::VecFrameAllocator,
+    util::error::WalnutError,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const ENTRY: usize = 0x1000_0000;
+const TEXT: &[u8] = &[0x13, 0, 0, 0]; // nop
+const DATA: &[u8] = &[0x11; 16];
+
+struct Segment {
+    flags: u32,
+    vaddr: usize,
+    data: &'static [u8],
+    mem_size: usize,
+}
+
+/// An ELF image with a program header for each of `segments`,
+/// followed by their data
+fn build_elf(entry: usize, segments: &[Segment]) -> Vec<u8> {
+    let mut image = Vec::new();
+    image.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    image.resize(16, 0);
+    image.extend_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+    image.extend_from_slice(&243u16.to_le_bytes()); // EM_RISCV
+    image.extend_from_slice(&1u32.to_le_bytes());
+    image.extend_from_slice(&(entry as u64).to_le_bytes());
+    image.extend_from_slice(&64u64.to_le_bytes()); // e_phoff
+    image.extend_from_slice(&0u64.to_le_bytes()); // e_shoff
+    image.extend_from_slice(&0u32.to_le_bytes());
+    image.extend_from_slice(&64u16.to_le_bytes());
+    image.extend_from_slice(&56u16.to_le_bytes());
+    image.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+    image.extend_from_slice(&[0; 6]);
+    assert_eq!(image.len(), 64);
+
+    let mut offset = 64 + 56 * segments.len();
+    for segment in segments {
+        image.extend_from_slice(&1u32.to_le_bytes()); // PT_LOAD
+        image.extend_from_slice(&segment.flags.to_le_bytes());
+        image.extend_from_slice(&(offset as u64).to_le_bytes());
+        image.extend_from_slice(&(segment.vaddr as u64).to_le_bytes());
+        image.extend_from_slice(&(segment.vaddr as u64).to_le_bytes());
+        image.extend_from_slice(&(segment.data.len() as u64).to_le_bytes());
+        image.extend_from_slice(&(segment.mem_size as u64).to_le_bytes());
+        image.extend_from_slice(&(PAGE_SIZE as u64).to_le_bytes());
+        offset += segment.data.len();
+    }
+    for segment in segments {
+        image.extend_from_slice(segment.data);
+    }
+    image
+}
+
+fn text() -> Segment {
+    Segment {
+        flags: PF_R | PF_X,
+        vaddr: ENTRY,
+        data: TEXT,
+        mem_size: TEXT.len(),
+    }
+}
+
+/// `.data` followed by `.bss`, starting just before a
+/// page boundary so both are split across pages
+fn data() -> Segment {
+    Segment {
+        flags: PF_R | PF_W,
+        vaddr: ENTRY + 0x1ff8,
+        data: DATA,
+        mem_size: 0x2000,
+    }
+}
+
+#[test_case]
+fn test_parse_segments() {
+    let image = build_elf(ENTRY, &[text(), data()]);
+    let elf = Elf::new(&image).expect("image failed to parse");
+    assert_eq!(elf.entry(), ENTRY);
+
+    let segments = elf.segments().collect::<Vec<_>>();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].vaddr, ENTRY);
+    assert!(segments[0].flags.get(SegmentFlags::EXEC));
+    assert!(!segments[0].flags.get(SegmentFlags::WRITE));
+    assert_eq!(elf.segment_data(&segments[0]), TEXT);
+    assert_eq!(segments[1].file_size, DATA.len());
+    assert_eq!(segments[1].mem_size, 0x2000);
+    assert!(segments[1].flags.get(SegmentFlags::WRITE));
+    assert_eq!(elf.segment_data(&segments[1]), DATA);
+}
+
+#[test_case]
+fn test_parse_bad_magic() {
+    let mut image = build_elf(ENTRY, &[text()]);
+    image[1] = b'F';
+    assert_eq!(Elf::new(&image).err(), Some(ElfError::BadMagic));
+}
+
+#[test_case]
+fn test_parse_wrong_machine() {
+    let mut image = build_elf(ENTRY, &[text()]);
+    // EM_X86_64
+    image[18..20].copy_from_slice(&62u16.to_le_bytes());
+    assert_eq!(Elf::new(&image).err(), Some(ElfError::WrongMachine(62)));
+}
+
+#[test_case]
+fn test_parse_truncated() {
+    let image = build_elf(ENTRY, &[text()]);
+    // the header alone
+    assert_eq!(Elf::new(&image[..32]).err(), Some(ElfError::Truncated));
+    // part way through the program header
+    assert_eq!(Elf::new(&image[..64 + 40]).err(), Some(ElfError::Truncated));
+    // missing the segment's data
+    assert_eq!(Elf::new(&image[..image.len() - 1]).err(), Some(ElfError::Truncated));
+}
+
+#[test_case]
+fn test_parse_file_larger_than_memory() {
+    let image = build_elf(ENTRY, &[Segment { mem_size: DATA.len() - 1, ..data() }]);
+    assert_eq!(Elf::new(&image).err(), Some(ElfError::Malformed));
+}
+
+/// Paging is off, so physical addresses are usable as they are
+const PHYS_OFFSET: usize = 0;
+
+const FRAME_COUNT: usize = 8;
+
+static mut FRAMES: [Page; FRAME_COUNT] = [const { Page { data: [0; PAGE_SIZE] } }; FRAME_COUNT];
+
+/// A table with an empty root, and frames full of garbage for the loader
+fn fresh_table() -> (OffsetPageTable, VecFrameAllocator) {
+    let frames = unsafe {
+        let frames = &mut *addr_of_mut!(FRAMES);
+        frames[0].data.fill(0);
+        for frame in frames[1..].iter_mut() {
+            frame.data.fill(0xaa);
+        }
+        frames.iter().map(|frame| frame as *const Page as usize).collect::<Vec<_>>()
+    };
+    let root = frames[0] as *mut PageTable;
+    let table = unsafe { OffsetPageTable::new(root, PHYS_OFFSET) };
+    (table, VecFrameAllocator::new(frames[1..].to_vec()))
+}
+
+fn read(table: &OffsetPageTable, va: usize) -> u8 {
+    let pa = table.translate_addr(VirtAddr::from_bits(va)).expect("address is not mapped");
+    unsafe { *((pa + PHYS_OFFSET) as *const u8) }
+}
+
+#[test_case]
+fn test_load_data_and_bss() {
+    let (mut table, mut frames) = fresh_table();
+    let image = build_elf(ENTRY, &[text(), data()]);
+    let entry = unsafe { load_elf_into(&mut table, &image, &mut frames) }.expect("image failed to load");
+    assert_eq!(entry, VirtAddr::from_bits(ENTRY));
+
+    for (i, &byte) in TEXT.iter().enumerate() {
+        assert_eq!(read(&table, ENTRY + i), byte);
+    }
+
+    let data = data();
+    for i in 0..data.mem_size {
+        let expected = DATA.get(i).copied().unwrap_or(0);
+        assert_eq!(read(&table, data.vaddr + i), expected, "wrong byte at offset {:#x}", i);
+    }
+    // the rest of the segment's first and last pages are zeroed too
+    assert_eq!(read(&table, ENTRY + 0x1000), 0);
+    assert_eq!(read(&table, data.vaddr + data.mem_size), 0);
+
+    let flags = |va: usize| table.walk(VirtAddr::from_bits(va)).unwrap().flags();
+    assert!(flags(ENTRY).get(PageTableFlags::EXEC));
+    assert!(!flags(ENTRY).get(PageTableFlags::WRITE));
+    assert!(flags(data.vaddr).get(PageTableFlags::WRITE));
+    assert!(!flags(data.vaddr).get(PageTableFlags::EXEC));
+}
+
+#[test_case]
+fn test_load_rejects_write_only() {
+    let (mut table, mut frames) = fresh_table();
+    let write_only = Segment { flags: PF_W, ..data() };
+    let image = build_elf(ENTRY, &[text(), write_only]);
+    let result = unsafe { load_elf_into(&mut table, &image, &mut frames) };
+    assert!(result.is_err());
+
+    // the text segment was unmapped again, and its frame handed back
+    assert_eq!(table.translate_addr(VirtAddr::from_bits(ENTRY)), None);
+    // the intermediate tables are kept
+    assert_eq!(frames.remaining(), FRAME_COUNT - 1 - 2);
+}
+
+#[test_case]
+fn test_load_rejects_non_canonical() {
+    let (mut table, mut frames) = fresh_table();
+    // runs from the top of the lower half into the hole above it
+    let straddling = Segment {
+        vaddr: (1 << 38) - PAGE_SIZE,
+        ..data()
+    };
+    let image = build_elf(ENTRY, &[straddling]);
+    let result = unsafe { load_elf_into(&mut table, &image, &mut frames) };
+    assert!(matches!(result, Err(WalnutError::Elf(ElfError::Malformed))));
+    assert_eq!(frames.remaining(), FRAME_COUNT - 1);
+}
+
+#[test_case]
+fn test_load_rejects_overflowing_segment() {
+    let (mut table, mut frames) = fresh_table();
+    let overflowing = Segment {
+        vaddr: usize::MAX - 0xf,
+        ..data()
+    };
+    let image = build_elf(ENTRY, &[overflowing]);
+    let result = unsafe { load_elf_into(&mut table, &image, &mut frames) };
+    assert!(matches!(result, Err(WalnutError::Elf(ElfError::Malformed))));
+}
@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{error::Error, panic::PanicInfo};
+
+use alloc::{format, string::ToString};
+use walnut::{
+    fdt::FdtError,
+    mem::{allocator::AllocationError, table::MapToError, vma::VmaError},
+    process::elf::ElfError,
+    util::error::WalnutError,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// `error` converted with `?`, as code spanning subsystems does
+fn convert<E>(error: E) -> walnut::Result<()>
+where
+    WalnutError: From<E>,
+{
+    Err(error)?
+}
+
+/// The converted error keeps the original as its source,
+/// and prefixes its message with the subsystem
+fn check(converted: WalnutError, prefix: &str, message: &str) {
+    assert_eq!(converted.to_string(), format!("{}: {}", prefix, message));
+    assert_eq!(converted.source().map(|e| e.to_string()).as_deref(), Some(message));
+}
+
+#[test_case]
+fn test_from_allocation_error() {
+    let converted = convert(AllocationError::new("out of blocks")).unwrap_err();
+    assert!(matches!(converted, WalnutError::Allocation(_)));
+    check(converted, "Allocation Error", "out of blocks");
+}
+
+#[test_case]
+fn test_from_fdt_error() {
+    let converted = convert(FdtError::BadMagic(0xdead_beef)).unwrap_err();
+    assert!(matches!(converted, WalnutError::Fdt(FdtError::BadMagic(0xdead_beef))));
+    check(converted, "Device Tree Error", "bad FDT magic (0xdeadbeef)");
+}
+
+#[test_case]
+fn test_from_elf_error() {
+    let converted = convert(ElfError::WrongMachine(62)).unwrap_err();
+    assert!(matches!(converted, WalnutError::Elf(ElfError::WrongMachine(62))));
+    check(converted, "ELF Error", "ELF is not for RISC-V (machine 62)");
+}
+
+#[test_case]
+fn test_from_map_to_error() {
+    let converted = convert(MapToError::AlreadyMapped).unwrap_err();
+    assert!(matches!(converted, WalnutError::Map(MapToError::AlreadyMapped)));
+    check(converted, "Mapping Error", "address is already mapped");
+}
+
+#[test_case]
+fn test_from_vma_error() {
+    let converted = convert(VmaError::Overlaps("heap")).unwrap_err();
+    assert!(matches!(converted, WalnutError::Vma(VmaError::Overlaps("heap"))));
+    check(converted, "VMA Error", "region overlaps another (heap)");
+}
+
+#[test_case]
+fn test_other_error() {
+    let error = WalnutError::new("something went wrong");
+    assert_eq!(error.to_string(), "something went wrong");
+    assert!(error.source().is_none());
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{
+    future::Future,
+    panic::PanicInfo,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+
+use walnut::{
+    executor::{Executor, Task},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Returns `Pending` once, waking itself so it's polled again
+struct YieldOnce<'a> {
+    yielded: bool,
+    polls: &'a AtomicUsize,
+}
+
+impl Future for YieldOnce<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[test_case]
+fn test_self_waking_task_completes() {
+    static POLLS: AtomicUsize = AtomicUsize::new(0);
+
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(YieldOnce { yielded: false, polls: &POLLS }));
+    assert!(!executor.is_empty());
+
+    executor.run_ready_tasks();
+    assert_eq!(POLLS.load(Ordering::Relaxed), 2);
+    assert!(executor.is_empty());
+}
+
+#[test_case]
+fn test_async_block_completes() {
+    static DONE: AtomicUsize = AtomicUsize::new(0);
+
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(async {
+        YieldOnce { yielded: false, polls: &DONE }.await;
+        YieldOnce { yielded: false, polls: &DONE }.await;
+    }));
+
+    executor.run_ready_tasks();
+    assert_eq!(DONE.load(Ordering::Relaxed), 4);
+    assert!(executor.is_empty());
+}
@@ -0,0 +1,256 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use alloc::vec::Vec;
+use walnut::{
+    fdt::{topology::Topology, Fdt, FdtError},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Writes a blob, a node or property at a time
+struct Builder {
+    structs: Vec<u8>,
+    strings: Vec<u8>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            structs: Vec::new(),
+            strings: Vec::new(),
+        }
+    }
+
+    fn token(&mut self, token: u32) -> &mut Self {
+        self.structs.extend_from_slice(&token.to_be_bytes());
+        self
+    }
+
+    fn pad(&mut self) {
+        self.structs.resize(self.structs.len().next_multiple_of(4), 0);
+    }
+
+    fn begin(&mut self, name: &str) -> &mut Self {
+        self.token(1);
+        self.structs.extend_from_slice(name.as_bytes());
+        self.structs.push(0);
+        self.pad();
+        self
+    }
+
+    fn end(&mut self) -> &mut Self {
+        self.token(2)
+    }
+
+    fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+        self.token(3);
+        let name_off = self.strings.len();
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+        self.structs.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        self.structs.extend_from_slice(&(name_off as u32).to_be_bytes());
+        self.structs.extend_from_slice(value);
+        self.pad();
+        self
+    }
+
+    fn prop_u32(&mut self, name: &str, value: u32) -> &mut Self {
+        self.prop(name, &value.to_be_bytes())
+    }
+
+    fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+        let value: Vec<u8> = cells.iter().flat_map(|cell| cell.to_be_bytes()).collect();
+        self.prop(name, &value)
+    }
+
+    /// The whole blob: header, an empty memory
+    /// reservation block, then the structure and strings blocks
+    fn finish(&mut self) -> Vec<u8> {
+        self.token(9);
+        let structs_off = 40 + 16;
+        let strings_off = structs_off + self.structs.len();
+        let total_size = strings_off + self.strings.len();
+
+        let mut blob = Vec::new();
+        for field in [
+            0xd00d_feed,
+            total_size as u32,
+            structs_off as u32,
+            strings_off as u32,
+            40,
+            17,
+            16,
+            0,
+            self.strings.len() as u32,
+            self.structs.len() as u32,
+        ] {
+            blob.extend_from_slice(&field.to_be_bytes());
+        }
+        blob.resize(structs_off, 0);
+        blob.extend_from_slice(&self.structs);
+        blob.extend_from_slice(&self.strings);
+        blob
+    }
+}
+
+fn small_blob() -> Vec<u8> {
+    Builder::new()
+        .begin("")
+        .prop_u32("#address-cells", 2)
+        .prop_u32("#size-cells", 2)
+        .begin("chosen")
+        .prop("bootargs", b"console=ttyS0\0")
+        .end()
+        .begin("soc")
+        .prop_u32("#address-cells", 2)
+        .prop_u32("#size-cells", 2)
+        .begin("serial@10000000")
+        .prop("compatible", b"ns16550a\0")
+        .prop_cells("reg", &[0, 0x1000_0000, 0, 0x100])
+        .end()
+        .begin("rtc@101000")
+        .prop("compatible", b"google,goldfish-rtc\0")
+        .end()
+        .end()
+        .end()
+        .finish()
+}
+
+#[test_case]
+fn test_walk_nodes_and_properties() {
+    let blob = small_blob();
+    let fdt = Fdt::new(&blob).expect("blob failed to parse");
+    assert_eq!(fdt.total_size(), blob.len());
+
+    let root = fdt.root().unwrap();
+    assert_eq!(root.name(), "");
+    assert_eq!(root.cell_sizes(), (2, 2));
+    assert_eq!(root.properties().count(), 2);
+    assert_eq!(root.children().map(|child| child.name()).collect::<Vec<_>>(), ["chosen", "soc"]);
+
+    let bootargs = fdt.find_node("/chosen").unwrap().property("bootargs").unwrap();
+    assert_eq!(bootargs.as_str(), Some("console=ttyS0"));
+
+    let soc = fdt.find_node("/soc").unwrap();
+    // the unit address can be left out
+    let serial = fdt.find_node("/soc/serial").unwrap();
+    assert_eq!(serial.name(), "serial@10000000");
+    assert!(serial.is_compatible("ns16550a"));
+    let reg: Vec<_> = serial.property("reg").unwrap().reg(soc.cell_sizes()).collect();
+    assert_eq!(reg, [(0x1000_0000, 0x100)]);
+
+    assert_eq!(soc.find_compatible("google,goldfish-rtc").map(|node| node.name()), Some("rtc@101000"));
+    assert!(fdt.find_node("/soc/virtio_mmio").is_none());
+}
+
+#[test_case]
+fn test_bad_magic() {
+    let mut blob = small_blob();
+    blob[0] = 0xde;
+    assert_eq!(Fdt::new(&blob).err(), Some(FdtError::BadMagic(0xde0d_feed)));
+}
+
+#[test_case]
+fn test_bad_version() {
+    let mut blob = small_blob();
+    // last compatible version
+    blob[24..28].copy_from_slice(&17u32.to_be_bytes());
+    assert_eq!(Fdt::new(&blob).err(), Some(FdtError::UnsupportedVersion(17)));
+}
+
+#[test_case]
+fn test_truncated() {
+    let blob = small_blob();
+    assert_eq!(Fdt::new(&blob[..2]).err(), Some(FdtError::Truncated));
+    // shorter than the header's total size
+    assert_eq!(Fdt::new(&blob[..blob.len() - 1]).err(), Some(FdtError::Truncated));
+}
+
+#[test_case]
+fn test_unbalanced_nodes() {
+    let blob = Builder::new().begin("").begin("soc").end().finish();
+    assert_eq!(Fdt::new(&blob).err(), Some(FdtError::Malformed));
+}
+
+/// Two harts, the second disabled and without Zkr, a CLINT and a PLIC
+fn topology_blob() -> Vec<u8> {
+    let mut builder = Builder::new();
+    builder
+        .begin("")
+        .prop_u32("#address-cells", 2)
+        .prop_u32("#size-cells", 2)
+        .begin("cpus")
+        .prop_u32("#address-cells", 1)
+        .prop_u32("#size-cells", 0)
+        .prop_u32("timebase-frequency", 10_000_000);
+    for (name, id, isa, status) in [
+        ("cpu@0", 0, "rv64imafdc_zicsr_zkr", "okay"),
+        ("cpu@1", 1, "rv64imafdc_zicsr", "disabled"),
+    ] {
+        builder
+            .begin(name)
+            .prop_u32("reg", id)
+            .prop("riscv,isa", &[isa.as_bytes(), b"\0"].concat())
+            .prop("status", &[status.as_bytes(), b"\0"].concat())
+            .begin("interrupt-controller")
+            .end()
+            .end();
+    }
+    builder
+        .end()
+        .begin("soc")
+        .prop_u32("#address-cells", 2)
+        .prop_u32("#size-cells", 2)
+        .begin("clint@2000000")
+        .prop("compatible", b"sifive,clint0\0riscv,clint0\0")
+        .prop_cells("reg", &[0, 0x200_0000, 0, 0x1_0000])
+        .end()
+        .begin("plic@c000000")
+        .prop("compatible", b"sifive,plic-1.0.0\0riscv,plic0\0")
+        .prop_cells("reg", &[0, 0xc00_0000, 0, 0x60_0000])
+        .prop_u32("riscv,ndev", 95)
+        .end()
+        .end()
+        .end()
+        .finish()
+}
+
+#[test_case]
+fn test_topology() {
+    let blob = topology_blob();
+    let fdt = Fdt::new(&blob).expect("blob failed to parse");
+    let topology = Topology::from_fdt(&fdt).expect("no topology found");
+
+    let harts: Vec<_> = topology.harts().map(|hart| (hart.id, hart.enabled, hart.has_zkr)).collect();
+    assert_eq!(harts, [(0, true, true), (1, false, false)]);
+    assert_eq!(topology.hart_count(), 1);
+    assert_eq!(topology.timebase_frequency, Some(10_000_000));
+    assert_eq!(topology.clint, Some(0x200_0000));
+    let plic = topology.plic.expect("no PLIC found");
+    assert_eq!((plic.base, plic.ndev), (0xc00_0000, 95));
+}
+
+#[test_case]
+fn test_topology_needs_cpus() {
+    let blob = small_blob();
+    let fdt = Fdt::new(&blob).unwrap();
+    assert_eq!(Topology::from_fdt(&fdt).err(), Some(FdtError::Malformed));
+}
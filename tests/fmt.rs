@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use alloc::format;
+use walnut::{util::fmt::ByteSize, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const KIB: usize = 1024;
+const MIB: usize = 1024 * KIB;
+const TIB: usize = 1024 * 1024 * MIB;
+
+#[test_case]
+fn test_bytes() {
+    assert_eq!(format!("{}", ByteSize(0)), "0 B");
+    assert_eq!(format!("{}", ByteSize(1023)), "1023 B");
+}
+
+#[test_case]
+fn test_whole_units() {
+    assert_eq!(format!("{}", ByteSize(KIB)), "1 KiB");
+    assert_eq!(format!("{}", ByteSize(MIB)), "1 MiB");
+    assert_eq!(format!("{}", ByteSize(1024 * MIB)), "1 GiB");
+}
+
+#[test_case]
+fn test_fractions() {
+    assert_eq!(format!("{}", ByteSize(3 * MIB + MIB / 2)), "3.5 MiB");
+    // rounded down, not up to 2 KiB
+    assert_eq!(format!("{}", ByteSize(2 * KIB - 1)), "1.9 KiB");
+}
+
+#[test_case]
+fn test_tib_is_the_largest_unit() {
+    assert_eq!(format!("{}", ByteSize(TIB)), "1 TiB");
+    assert_eq!(format!("{}", ByteSize(2048 * TIB)), "2048 TiB");
+    assert_eq!(format!("{}", ByteSize(usize::MAX)), "16777215.9 TiB");
+}
@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{
+    panic::PanicInfo,
+    ptr::addr_of,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{boxed::Box, vec::Vec};
+use walnut::{
+    mem::allocator::{HeapStats, ALLOCATOR},
+    task, BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+fn stats() -> HeapStats {
+    unsafe { (*addr_of!(ALLOCATOR)).stats() }
+}
+
+#[test_case]
+fn test_stats_track_allocations() {
+    let before = stats();
+
+    let a = Box::new([0u8; 100]);
+    let b = Box::new(0u64);
+    let during = stats();
+    assert_eq!(during.used, before.used + 108);
+    assert_eq!(during.allocations, before.allocations + 2);
+    assert!(during.free < before.free);
+
+    drop(a);
+    assert_eq!(stats().allocations, before.allocations + 1);
+    drop(b);
+    let after = stats();
+    assert_eq!(after.used, before.used);
+    assert_eq!(after.allocations, before.allocations);
+}
+
+#[test_case]
+fn test_stats_track_reallocation() {
+    let before = stats();
+
+    let mut v = Vec::<u8>::with_capacity(16);
+    v.extend_from_slice(&[1; 64]);
+    assert_eq!(stats().allocations, before.allocations + 1);
+    assert_eq!(stats().used, before.used + v.capacity());
+
+    drop(v);
+    assert_eq!(stats(), HeapStats { free: stats().free, ..before });
+}
+
+#[test_case]
+fn test_failed_allocation_is_not_counted() {
+    let before = stats();
+
+    let mut v = Vec::<u8>::new();
+    // far more than the whole heap
+    assert!(v.try_reserve(1 << 40).is_err());
+    assert_eq!(stats().used, before.used);
+    assert_eq!(stats().allocations, before.allocations);
+    assert!(stats().largest_free <= stats().free);
+}
+
+static CHURNED: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocate and free over and over, so preemption and
+/// the other harts catch the free list half way through
+fn churn_once() {
+    for i in 0..500 {
+        let v: Vec<usize> = (0..i % 32).collect();
+        let b = Box::new(v.len());
+        assert_eq!(*b, i % 32);
+    }
+}
+
+fn churn() {
+    churn_once();
+    CHURNED.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test_case]
+fn test_concurrent_allocations() {
+    const THREADS: usize = 8;
+    for _ in 0..THREADS {
+        task::spawn(churn);
+    }
+    while CHURNED.load(Ordering::Relaxed) < THREADS {
+        churn_once();
+        task::yield_now();
+    }
+    // walking the free list doesn't run off into freed memory
+    let stats = stats();
+    assert!(stats.largest_free <= stats.free);
+}
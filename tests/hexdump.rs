@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use alloc::string::String;
+use walnut::{util::hexdump::write_hexdump, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+fn dump(start: usize, data: &[u8]) -> String {
+    let mut out = String::new();
+    write_hexdump(&mut out, start, data).unwrap();
+    out
+}
+
+#[test_case]
+fn test_aligned_line() {
+    assert_eq!(
+        dump(0x8020_0000, b"0123456789abcdef"),
+        "0000000080200000  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|\r\n"
+    );
+}
+
+#[test_case]
+fn test_unaligned_start_and_partial_end() {
+    assert_eq!(
+        dump(0x1003, b"Hello,\tworld!\x00\xffABCD"),
+        concat!(
+            "0000000000001000           48 65 6c 6c 6f  2c 09 77 6f 72 6c 64 21  |   Hello,.world!|\r\n",
+            "0000000000001010  00 ff 41 42 43 44                                 |..ABCD|\r\n",
+        )
+    );
+}
+
+#[test_case]
+fn test_empty() {
+    assert_eq!(dump(0x1000, b""), "");
+}
@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    drivers::console::{self, OutputSink},
+    kassert_eq,
+    sync::spinlock::SpinLock,
+    testing, BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const CAPTURE_SIZE: usize = 1024;
+
+/// Remembers the start of what's printed once it's registered.
+///
+/// This writes into a fixed buffer, since sinks are called with the
+/// console locked, and a failed allocation prints why it failed.
+struct Capture(SpinLock<([u8; CAPTURE_SIZE], usize)>);
+
+impl OutputSink for Capture {
+    fn write_str(&self, s: &str) {
+        let mut guard = self.0.lock();
+        let (buf, len) = &mut *guard;
+        let n = s.len().min(buf.len() - *len);
+        buf[*len..*len + n].copy_from_slice(&s.as_bytes()[..n]);
+        *len += n;
+    }
+}
+
+static CAPTURE: Capture = Capture(SpinLock::new(([0; CAPTURE_SIZE], 0)));
+
+/// Whether the failure was reported with both sides and the message
+fn reported_operands() -> bool {
+    let guard = CAPTURE.0.lock();
+    let (buf, len) = &*guard;
+    let Ok(captured) = core::str::from_utf8(&buf[..*len]) else {
+        return false;
+    };
+    captured.contains("assertion failed: `one + 2 == 4` at tests/kassert.rs:")
+        && captured.contains("\n  left: 3\n right: 4\n")
+        && captured.contains("adding 2 to 1")
+}
+
+#[test_case]
+fn test_kassert_eq_reports_operands() {
+    testing::expect_failure(reported_operands);
+    console::register(&CAPTURE).expect("no room for another sink");
+    let one = core::hint::black_box(1);
+    kassert_eq!(one + 2, 4, "adding 2 to {}", one);
+}
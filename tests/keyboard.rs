@@ -0,0 +1,143 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks, async_iterator)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{
+    async_iter::AsyncIterator,
+    panic::PanicInfo,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use alloc::{sync::Arc, task::Wake, vec::Vec};
+
+use walnut::{
+    drivers::keyboard::{self, DecodedKey, Decoder, KeyStream, SpecialKey},
+    sync::ring::RingBuffer,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Feed `bytes` to a fresh decoder, returning the single key they make
+fn decode(bytes: &[u8]) -> Option<DecodedKey> {
+    let mut decoder = Decoder::new();
+    let keys: Vec<_> = bytes.iter().filter_map(|&b| decoder.feed(b)).collect();
+    assert!(keys.len() <= 1, "{:?} decoded to {:?}", bytes, keys);
+    keys.first().copied()
+}
+
+#[test_case]
+fn test_plain_ascii() {
+    assert_eq!(decode(b"a"), Some(DecodedKey::Char('a')));
+    assert_eq!(decode(b"Z"), Some(DecodedKey::Char('Z')));
+    assert_eq!(decode(b" "), Some(DecodedKey::Char(' ')));
+    assert_eq!(decode(b"~"), Some(DecodedKey::Char('~')));
+    // other control characters are ignored
+    assert_eq!(decode(&[0x01]), None);
+}
+
+#[test_case]
+fn test_control_keys() {
+    assert_eq!(decode(b"\r"), Some(DecodedKey::Special(SpecialKey::Enter)));
+    assert_eq!(decode(b"\n"), Some(DecodedKey::Special(SpecialKey::Enter)));
+    assert_eq!(decode(&[0x7f]), Some(DecodedKey::Special(SpecialKey::Backspace)));
+    assert_eq!(decode(&[0x08]), Some(DecodedKey::Special(SpecialKey::Backspace)));
+    assert_eq!(decode(b"\t"), Some(DecodedKey::Special(SpecialKey::Tab)));
+}
+
+#[test_case]
+fn test_csi_arrows() {
+    assert_eq!(decode(b"\x1b[A"), Some(DecodedKey::Special(SpecialKey::ArrowUp)));
+    assert_eq!(decode(b"\x1b[B"), Some(DecodedKey::Special(SpecialKey::ArrowDown)));
+    assert_eq!(decode(b"\x1b[C"), Some(DecodedKey::Special(SpecialKey::ArrowRight)));
+    assert_eq!(decode(b"\x1b[D"), Some(DecodedKey::Special(SpecialKey::ArrowLeft)));
+    assert_eq!(decode(b"\x1b[H"), Some(DecodedKey::Special(SpecialKey::Home)));
+    assert_eq!(decode(b"\x1b[F"), Some(DecodedKey::Special(SpecialKey::End)));
+    // unknown sequences are swallowed whole
+    assert_eq!(decode(b"\x1b[Z"), None);
+}
+
+#[test_case]
+fn test_aborted_escape() {
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.feed(0x1b), None);
+    // the byte after a lone escape is dropped
+    assert_eq!(decoder.feed(b'x'), Some(DecodedKey::Special(SpecialKey::Escape)));
+    // and the decoder is back to normal
+    assert_eq!(decoder.feed(b'y'), Some(DecodedKey::Char('y')));
+}
+
+#[test_case]
+fn test_ring_push_pop() {
+    let ring: RingBuffer<u8, 4> = RingBuffer::new();
+    assert!(ring.is_empty());
+    assert_eq!(ring.pop(), None);
+
+    ring.push(1).unwrap();
+    ring.push(2).unwrap();
+    assert_eq!(ring.len(), 2);
+    assert_eq!(ring.pop(), Some(1));
+    assert_eq!(ring.pop(), Some(2));
+    assert_eq!(ring.pop(), None);
+}
+
+#[test_case]
+fn test_ring_full() {
+    let ring: RingBuffer<u8, 4> = RingBuffer::new();
+    for i in 0..4 {
+        ring.push(i).unwrap();
+    }
+    assert_eq!(ring.push(4), Err(4));
+    assert_eq!(ring.len(), 4);
+
+    // wrapping around reuses the freed slot
+    assert_eq!(ring.pop(), Some(0));
+    ring.push(4).unwrap();
+    let popped: [_; 4] = core::array::from_fn(|_| ring.pop());
+    assert_eq!(popped, [Some(1), Some(2), Some(3), Some(4)]);
+    assert!(ring.is_empty());
+}
+
+/// Counts how many times it's woken
+struct CountingWaker(AtomicUsize);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test_case]
+fn test_key_stream_wakes_on_key() {
+    let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+    let waker = Waker::from(counter.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut keys = KeyStream::new();
+
+    assert_eq!(Pin::new(&mut keys).poll_next(&mut cx), Poll::Pending);
+    assert_eq!(counter.0.load(Ordering::Relaxed), 0);
+
+    keyboard::handle_byte(b'x');
+    assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+    assert_eq!(Pin::new(&mut keys).poll_next(&mut cx), Poll::Ready(Some(DecodedKey::Char('x'))));
+
+    // the waker is only used once
+    keyboard::handle_byte(b'y');
+    assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+    assert_eq!(Pin::new(&mut keys).poll_next(&mut cx), Poll::Ready(Some(DecodedKey::Char('y'))));
+}
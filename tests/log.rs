@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use alloc::string::String;
+use walnut::{
+    drivers::console::{self, OutputSink},
+    init::log::{self, Level},
+    sync::spinlock::SpinLock,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Remembers everything printed while it's registered
+struct Capture(SpinLock<String>);
+
+impl OutputSink for Capture {
+    fn write_str(&self, s: &str) {
+        self.0.lock().push_str(s);
+    }
+}
+
+static CAPTURE: Capture = Capture(SpinLock::new(String::new()));
+
+#[test_case]
+fn test_level_filtering() {
+    console::register(&CAPTURE).expect("no room for another sink");
+    log::set_level(Level::Warn);
+    walnut::trace!("trace message");
+    walnut::debug!("debug message");
+    walnut::info!("info message");
+    walnut::warn!("warn message");
+    walnut::error!("error message");
+    log::set_level(Level::Trace);
+    assert!(console::unregister(&CAPTURE));
+
+    let captured = CAPTURE.0.lock();
+    for dropped in ["trace", "debug", "info"] {
+        assert!(!captured.contains(dropped), "{} was logged", dropped);
+    }
+    assert!(captured.contains("[WARN ] warn message"));
+    assert!(captured.contains("[ERROR] error message"));
+}
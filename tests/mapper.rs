@@ -0,0 +1,417 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::{panic::PanicInfo, ptr::addr_of_mut};
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use walnut::{
+    mem::{
+        addr::VirtAddr,
+        pages::{EmptyFrameAllocator, Page, Size1GiB, Size2MiB, Size4KiB, PAGE_SIZE},
+        table::{
+            MapToError, MappedFrame, OffsetPageTable, PageTable, PageTableAllocator, PageTableEntry, PageTableFlags,
+            TranslateResult,
+        },
+    },
+    testing::VecFrameAllocator,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Paging is off, so physical addresses are usable as they are
+const PHYS_OFFSET: usize = 0;
+
+static mut FRAMES: [Page; 4] = [const { Page { data: [0; PAGE_SIZE] } }; 4];
+
+/// A table with an empty root, and the frames left for it to use
+fn fresh_table() -> (OffsetPageTable, Vec<usize>) {
+    let frames = unsafe {
+        let frames = &mut *addr_of_mut!(FRAMES);
+        for frame in frames.iter_mut() {
+            frame.data.fill(0);
+        }
+        frames.iter().map(|frame| frame as *const Page as usize).collect::<Vec<_>>()
+    };
+    let root = frames[0] as *mut PageTable;
+    (unsafe { OffsetPageTable::new(root, PHYS_OFFSET) }, frames[1..].to_vec())
+}
+
+fn read_write() -> PageTableFlags {
+    PageTableFlags::new().with(PageTableFlags::READ, true).with(PageTableFlags::WRITE, true)
+}
+
+const VA: usize = 0x4000_0000;
+const PA: usize = 0x8020_0000;
+
+#[test_case]
+fn test_map_to_without_frames_fails() {
+    let (mut table, _) = fresh_table();
+    let flush = unsafe { table.map_to(VirtAddr::from_bits(VA), PA, read_write(), 0, &mut EmptyFrameAllocator) };
+    assert_eq!(flush.err(), Some(MapToError::FrameAllocationFailed));
+    assert_eq!(table.translate_addr(VirtAddr::from_bits(VA)), None);
+}
+
+#[test_case]
+fn test_map_to_runs_out_part_way() {
+    let (mut table, frames) = fresh_table();
+    // a 4KiB page needs two intermediate tables
+    let mut allocator = VecFrameAllocator::new(frames[..1].to_vec());
+    let flush = unsafe { table.map_to(VirtAddr::from_bits(VA), PA, read_write(), 0, &mut allocator) };
+    assert_eq!(flush.err(), Some(MapToError::FrameAllocationFailed));
+    assert_eq!(allocator.remaining(), 0);
+}
+
+#[test_case]
+fn test_map_to_succeeds() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let flush = unsafe { table.map_to(VirtAddr::from_bits(VA), PA, read_write(), 0, &mut allocator) };
+    flush.expect("mapping with enough frames failed").ignore();
+
+    assert_eq!(allocator.remaining(), 1);
+    assert_eq!(table.translate_addr(VirtAddr::from_bits(VA + 0x123)), Some(PA + 0x123));
+}
+
+#[test_case]
+fn test_map_to_megapage_needs_one_table() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let flush = unsafe { table.map_to(VirtAddr::from_bits(VA), PA & !0x1f_ffff, read_write(), 1, &mut allocator) };
+    flush.expect("mapping a megapage failed").ignore();
+
+    assert_eq!(allocator.remaining(), 2);
+    assert_eq!(table.translate_addr(VirtAddr::from_bits(VA + 0x12345)), Some((PA & !0x1f_ffff) + 0x12345));
+}
+
+#[test_case]
+fn test_flush_carries_the_page() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let va = VirtAddr::from_bits(VA);
+
+    let flush = unsafe { table.map_to(va, PA, read_write(), 0, &mut allocator) }.unwrap();
+    assert_eq!(flush.addr(), va);
+    // paging is off, so there's nothing stale to flush, but it's harmless
+    flush.flush();
+
+    let (_, _, flush) = unsafe { table.unmap(va) }.unwrap();
+    assert_eq!(flush.addr(), va);
+    flush.ignore();
+}
+
+#[test_case]
+fn test_unmap() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let (va, next) = (VirtAddr::from_bits(VA), VirtAddr::from_bits(VA + PAGE_SIZE));
+    unsafe {
+        table.map_to(va, PA, read_write(), 0, &mut allocator).unwrap().ignore();
+        table.map_to(next, PA + PAGE_SIZE, read_write(), 0, &mut allocator).unwrap().ignore();
+    }
+
+    let (pa, flags, flush) = unsafe { table.unmap(va) }.expect("mapped page was not unmapped");
+    flush.ignore();
+    assert_eq!(pa, PA);
+    assert!(flags.get(PageTableFlags::READ) && flags.get(PageTableFlags::WRITE));
+    assert!(!flags.get(PageTableFlags::EXEC));
+    assert_eq!(table.translate_addr(va), None);
+    assert!(table.walk(va).is_none());
+    // only the one page goes
+    assert_eq!(table.translate_addr(next), Some(PA + PAGE_SIZE));
+
+    assert!(unsafe { table.unmap(va) }.is_none());
+    // and the address can be mapped again
+    unsafe { table.map_to(va, PA, read_write(), 0, &mut allocator) }.expect("remapping failed").ignore();
+}
+
+#[test_case]
+fn test_unmap_leaves_huge_pages() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let mega = VirtAddr::from_mega_indices(1, 3);
+    unsafe { table.map_to(mega, 0x8020_0000, read_write(), 1, &mut allocator) }.unwrap().ignore();
+
+    let inside = VirtAddr::from_bits(mega.bits() + PAGE_SIZE);
+    assert!(unsafe { table.unmap(inside) }.is_none());
+    unsafe { table.unmap_range(inside, 1, &mut allocator) };
+    // the whole megapage is still there, and its frame wasn't handed out
+    assert_eq!(table.translate_addr(mega), Some(0x8020_0000));
+    assert_eq!(table.translate_addr(inside), Some(0x8020_0000 + PAGE_SIZE));
+    assert_eq!(allocator.remaining(), 2);
+}
+
+#[test_case]
+fn test_map_to_inside_huge_page_fails() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let giga = VirtAddr::from_giga_index(1);
+    let mega = VirtAddr::from_mega_indices(2, 1);
+    unsafe {
+        table.map_to(giga, 0x8000_0000, read_write(), 2, &mut allocator).unwrap().ignore();
+        table.map_to(mega, 0x8020_0000, read_write(), 1, &mut allocator).unwrap().ignore();
+    }
+    let remaining = allocator.remaining();
+
+    // the huge pages' frames must not be taken for tables
+    for (va, lvl) in [(VirtAddr::from_indices(1, 3, 4), 0), (VirtAddr::from_mega_indices(1, 3), 1), (VirtAddr::from_indices(2, 1, 4), 0)] {
+        let result = unsafe { table.map_to(va, PA, read_write(), lvl, &mut allocator) };
+        assert_eq!(result.err(), Some(MapToError::ParentIsHugePage));
+    }
+    assert_eq!(allocator.remaining(), remaining);
+    assert_eq!(table.translate_addr(VirtAddr::from_indices(1, 3, 4)), Some(0x8000_0000 + (3 << 21) + (4 << 12)));
+    assert_eq!(table.translate_addr(mega), Some(0x8020_0000));
+}
+
+#[test_case]
+fn test_map_to_already_mapped_fails() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let va = VirtAddr::from_bits(VA);
+    unsafe {
+        table.map_to(va, PA, read_write(), 0, &mut allocator).unwrap().ignore();
+        let again = table.map_to(va, PA + PAGE_SIZE, read_write(), 0, &mut allocator);
+        assert_eq!(again.err(), Some(MapToError::AlreadyMapped));
+        // a huge page over the table holding the page would hide it
+        let over = table.map_to(va, 0x8000_0000, read_write(), 1, &mut allocator);
+        assert_eq!(over.err(), Some(MapToError::AlreadyMapped));
+    }
+    assert_eq!(table.translate_addr(va), Some(PA));
+}
+
+#[test_case]
+fn test_map_range_and_unmap_range() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames.clone());
+    let va = VirtAddr::from_bits(VA);
+
+    unsafe { table.map_range(va, 1, read_write(), &mut allocator) }.expect("mapping one page failed");
+    assert_eq!(allocator.remaining(), 0);
+    let pa = table.translate_addr(va).expect("page was not mapped");
+    assert!(frames.contains(&pa));
+
+    unsafe { table.unmap_range(va, 1, &mut allocator) };
+    assert_eq!(table.translate_addr(va), None);
+    assert_eq!(allocator.remaining(), 1);
+}
+
+#[test_case]
+fn test_map_range_rolls_back() {
+    let (mut table, frames) = fresh_table();
+    // the first page takes a frame and two tables, leaving nothing for the second
+    let mut allocator = VecFrameAllocator::new(frames);
+    let va = VirtAddr::from_bits(VA);
+
+    let result = unsafe { table.map_range(va, 2, read_write(), &mut allocator) };
+    assert_eq!(result, Err(MapToError::FrameAllocationFailed));
+    assert_eq!(table.translate_addr(va), None);
+    // the first page's frame was handed back, the tables are kept for reuse
+    assert_eq!(allocator.remaining(), 1);
+}
+
+#[test_case]
+fn test_identity_map() {
+    const UART: usize = 0x1000_0000;
+    let (mut table, frames) = fresh_table();
+
+    let failed = unsafe { table.identity_map(UART, read_write(), &mut EmptyFrameAllocator) };
+    assert_eq!(failed.err(), Some(MapToError::FrameAllocationFailed));
+
+    let mut allocator = VecFrameAllocator::new(frames);
+    // the address is rounded down to its page
+    unsafe { table.identity_map(UART + 5, read_write(), &mut allocator) }.unwrap().ignore();
+    assert_eq!(table.translate_addr(VirtAddr::from_bits(UART + 5)), Some(UART + 5));
+    assert_eq!(table.translate_addr(VirtAddr::from_bits(UART + PAGE_SIZE)), None);
+
+    let again = unsafe { table.identity_map(UART, read_write(), &mut allocator) };
+    assert_eq!(again.err(), Some(MapToError::AlreadyMapped));
+}
+
+#[test_case]
+fn test_map_to_checks_permissions() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let flags = |read, write, exec| {
+        PageTableFlags::new()
+            .with(PageTableFlags::READ, read)
+            .with(PageTableFlags::WRITE, write)
+            .with(PageTableFlags::EXEC, exec)
+    };
+    let map = |table: &mut OffsetPageTable, allocator: &mut VecFrameAllocator, i, flags| unsafe {
+        table.map_to(VirtAddr::from_indices(1, 0, i), PA, flags, 0, allocator).map(|flush| flush.ignore())
+    };
+
+    assert_eq!(map(&mut table, &mut allocator, 0, flags(true, true, true)), Err(MapToError::WritableAndExecutable));
+    assert_eq!(map(&mut table, &mut allocator, 0, flags(false, true, true)), Err(MapToError::WritableAndExecutable));
+    assert_eq!(map(&mut table, &mut allocator, 0, flags(false, true, false)), Err(MapToError::WriteWithoutRead));
+    assert_eq!(map(&mut table, &mut allocator, 0, flags(false, false, false)), Err(MapToError::NoPermissions));
+    // nothing was written for the refused mappings
+    assert_eq!(allocator.remaining(), 3);
+
+    // code and data get the permissions they need and no more
+    assert_eq!(map(&mut table, &mut allocator, 1, flags(true, false, true)), Ok(()));
+    assert_eq!(map(&mut table, &mut allocator, 2, flags(true, true, false)), Ok(()));
+    assert_eq!(map(&mut table, &mut allocator, 3, flags(false, false, true)), Ok(()));
+}
+
+#[test_case]
+fn test_table_indices() {
+    let va = VirtAddr::from_indices(0x1ff, 3, 0x42);
+    assert_eq!(va.table_indices(), (0x1ff, 3, 0x42));
+    // the top index sign extends into the upper half
+    assert_eq!(va.bits(), 0xffff_ffff_c000_0000 | 3 << 21 | 0x42 << 12);
+}
+
+#[test_case]
+fn test_index_constructors() {
+    assert_eq!(VirtAddr::from_indices(0, 0, 0), VirtAddr::ZERO);
+    assert_eq!(VirtAddr::from_indices(1, 2, 3).bits(), 1 << 30 | 2 << 21 | 3 << 12);
+    assert_eq!(VirtAddr::from_mega_indices(2, 5).bits(), 2 << 30 | 5 << 21);
+    assert!(VirtAddr::from_mega_indices(2, 5).is_aligned_to_page::<Size2MiB>());
+    assert_eq!(VirtAddr::from_giga_index(3).bits(), 3 << 30);
+    assert!(VirtAddr::from_giga_index(3).is_aligned_to_page::<Size1GiB>());
+
+    // the last gigapage of the lower half stays there
+    assert_eq!(VirtAddr::from_giga_index(0xff).bits(), 0x3f_c000_0000);
+    // and the next is the first of the upper half, sign extended
+    let upper = VirtAddr::from_giga_index(0x100);
+    assert_eq!(upper.bits(), 0xffff_ffc0_0000_0000);
+    assert!(upper.is_canonical());
+    assert_eq!(upper.table_indices(), (0x100, 0, 0));
+    assert_eq!(VirtAddr::from_mega_indices(0x100, 1).bits(), 0xffff_ffc0_0020_0000);
+    assert_eq!(VirtAddr::from_indices(0x1ff, 0x1ff, 0x1ff).bits(), 0xffff_ffff_ffff_f000);
+}
+
+#[test_case]
+fn test_entry_address_and_flags() {
+    let flags = read_write().with(PageTableFlags::VALID, true).with(PageTableFlags::DIRTY, true);
+    let mut entry = PageTableEntry::new();
+    entry.set_addr(PA, flags);
+    assert_eq!(entry.addr(), PA);
+    assert_eq!(entry.flags().bits(), flags.bits());
+    assert_eq!(entry.bits(), (PA >> 12) << 10 | flags.bits());
+
+    // the offset into the page isn't stored
+    entry.set_addr(PA + 0x123, flags);
+    assert_eq!(entry.addr(), PA);
+
+    // the highest Sv39 physical page, all 44 bits of the PPN,
+    // isn't sign extended or spilled into the reserved bits
+    let top = (1 << 56) - PAGE_SIZE;
+    entry.set_addr(top, PageTableFlags::new());
+    assert_eq!(entry.addr(), top);
+    assert_eq!(entry.bits() >> 54, 0);
+    assert_eq!(entry.flags().bits(), 0);
+    entry.set_addr(1 << 55, flags);
+    assert_eq!(entry.addr(), 1 << 55);
+
+    // page zero, with every flag, keeps them apart too
+    let all = PageTableFlags::from_bits(0xff);
+    entry.set_addr(0, all);
+    assert_eq!((entry.addr(), entry.flags().bits()), (0, all.bits()));
+}
+
+#[test_case]
+fn test_containing_huge_pages() {
+    // a 4KiB page in the middle of the second 2MiB page of a gigapage
+    let page = VirtAddr::from_indices(1, 1, 0x100);
+    assert!(page.is_aligned_to_page::<Size4KiB>());
+    assert!(!page.is_aligned_to_page::<Size2MiB>());
+
+    assert_eq!(page.align_down_to_page::<Size2MiB>().bits(), VirtAddr::from_mega_indices(1, 1).bits());
+    assert_eq!(page.align_up_to_page::<Size2MiB>().bits(), VirtAddr::from_mega_indices(1, 2).bits());
+    assert_eq!(page.align_down_to_page::<Size1GiB>().bits(), VirtAddr::from_giga_index(1).bits());
+}
+
+#[test_case]
+fn test_translate_page_sizes() {
+    let (mut table, frames) = fresh_table();
+    let mut allocator = VecFrameAllocator::new(frames);
+    let read_only = PageTableFlags::new().with(PageTableFlags::READ, true);
+
+    let small = VirtAddr::from_indices(1, 0, 1);
+    let mega = VirtAddr::from_mega_indices(1, 1);
+    let giga = VirtAddr::from_giga_index(2);
+    unsafe {
+        table.map_to(small, 0x8000_1000, read_write(), 0, &mut allocator).unwrap().ignore();
+        table.map_to(mega, 0x8020_0000, read_only, 1, &mut allocator).unwrap().ignore();
+        table.map_to(giga, 0xc000_0000, read_write(), 2, &mut allocator).unwrap().ignore();
+    }
+
+    let cases = [
+        (small, MappedFrame::Size4KiB(0x8000_1000), 0x10),
+        (mega, MappedFrame::Size2MiB(0x8020_0000), 0x1234),
+        (giga, MappedFrame::Size1GiB(0xc000_0000), 0x12_3456),
+    ];
+    for (va, expected, offset) in cases {
+        match table.translate(VirtAddr::from_bits(va.bits() + offset)) {
+            TranslateResult::Mapped { frame, offset: found, flags } => {
+                assert_eq!(frame, expected);
+                assert_eq!(found, offset);
+                assert!(flags.get(PageTableFlags::VALID));
+                assert_eq!(flags.get(PageTableFlags::WRITE), expected != MappedFrame::Size2MiB(0x8020_0000));
+            }
+            TranslateResult::NotMapped => panic!("{:#x} is not mapped", va.bits()),
+        }
+    }
+
+    assert!(matches!(table.translate(VirtAddr::from_giga_index(3)), TranslateResult::NotMapped));
+}
+
+#[test_case]
+fn test_page_table_allocator_zeroes() {
+    let (_, frames) = fresh_table();
+    // dirty the frame, as a previous user could have
+    unsafe { (frames[0] as *mut u8).write_bytes(0xaa, PAGE_SIZE) };
+
+    let mut frame_allocator = VecFrameAllocator::new(frames[..1].to_vec());
+    let mut tables = PageTableAllocator::new(&mut frame_allocator, PHYS_OFFSET);
+    let (pa, table) = tables.allocate().expect("no table allocated");
+
+    assert_eq!(pa, frames[0]);
+    assert_eq!(table as usize % PAGE_SIZE, 0);
+    let bytes = unsafe { core::slice::from_raw_parts(table as *const u8, PAGE_SIZE) };
+    assert!(bytes.iter().all(|&b| b == 0));
+
+    assert!(tables.allocate().is_none());
+}
+
+#[test_case]
+fn test_null_and_max() {
+    assert!(VirtAddr::ZERO.is_null());
+    assert!(!VirtAddr::MAX.is_null());
+    // the top of the upper half is still at the last table index
+    assert_eq!(VirtAddr::MAX.table_indices(), (0x1ff, 0x1ff, 0x1ff));
+    assert_eq!(VirtAddr::from_indices(0x1ff, 0x1ff, 0x1ff).bits() | 0xfff, VirtAddr::MAX.bits());
+}
+
+#[test_case]
+fn test_pages_key_a_btreemap() {
+    let mut mapped = BTreeMap::new();
+    for i in [3, 1, 2] {
+        let page = VirtAddr::from_indices(0, 0, i);
+        mapped.insert(page, i * PAGE_SIZE);
+    }
+
+    // any address in a page finds it once aligned down
+    let addr = VirtAddr::from_bits(VirtAddr::from_indices(0, 0, 2).bits() + 0x123);
+    assert_eq!(mapped.get(&addr.align_down(PAGE_SIZE)), Some(&(2 * PAGE_SIZE)));
+    assert_eq!(mapped.get(&VirtAddr::from_indices(0, 0, 4)), None);
+
+    let pages: Vec<_> = mapped.keys().map(|page| page.table_indices().2).collect();
+    assert_eq!(pages, [1, 2, 3]);
+}
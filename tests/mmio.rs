@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use alloc::{boxed::Box, vec};
+use walnut::{
+    drivers::mmio::{Mmio, Volatile},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Stands in for a device's register block
+fn fake_block() -> Box<[u32]> {
+    vec![0u32; 8].into_boxed_slice()
+}
+
+#[test_case]
+fn test_mmio_round_trip() {
+    let mut block = fake_block();
+    let base = Mmio::<u32>::new(block.as_mut_ptr() as usize);
+
+    unsafe {
+        base.write(0xdead_beef);
+        base.reg(3).write(0x1234_5678);
+        // the same register as `reg(3)`, by its byte offset
+        assert_eq!(base.offset(12).read(), 0x1234_5678);
+        assert_eq!(base.read(), 0xdead_beef);
+    }
+    assert_eq!(base.reg(3).addr(), base.addr() + 12);
+    assert_eq!(block[..4], [0xdead_beef, 0, 0, 0x1234_5678]);
+}
+
+#[test_case]
+fn test_mmio_byte_registers() {
+    let mut block = fake_block();
+    let base = Mmio::<u8>::new(block.as_mut_ptr() as usize);
+
+    // registers of a different width than the block's are
+    // reached by offset and a new `Mmio` of their own type
+    unsafe {
+        base.reg(1).write(0xab);
+        Mmio::<u32>::new(base.offset(4).addr()).write(0x0102_0304);
+        assert_eq!(base.reg(1).read(), 0xab);
+        assert_eq!(base.offset(4).read(), 0x04);
+    }
+    assert_eq!(block[0], 0xab00);
+    assert_eq!(block[1], 0x0102_0304);
+}
+
+#[repr(C)]
+struct Registers {
+    data: Volatile<u32>,
+    control: Volatile<u32>,
+}
+
+#[test_case]
+fn test_volatile_fields() {
+    let regs = Registers {
+        data: Volatile::new(1),
+        control: Volatile::new(0),
+    };
+    regs.data.write(7);
+    regs.control.update(|v| v | 0b100);
+    assert_eq!(regs.data.read(), 7);
+    assert_eq!(regs.control.read(), 0b100);
+}
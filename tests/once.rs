@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use walnut::{sync::once::Once, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_call_once_runs_once() {
+    let calls = AtomicUsize::new(0);
+    let once = Once::new();
+    assert_eq!(once.get(), None);
+
+    for _ in 0..3 {
+        let value = once.call_once(|| calls.fetch_add(1, Ordering::Relaxed) + 42);
+        assert_eq!(*value, 42);
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    assert!(once.is_completed());
+    assert_eq!(once.get(), Some(&42));
+}
+
+#[test_case]
+fn test_reset_initializes_again() {
+    let once = Once::new();
+    once.call_once(|| 1);
+    unsafe { once.reset() };
+    assert!(!once.is_completed());
+    assert_eq!(*once.call_once(|| 2), 2);
+    // resetting an empty `Once` is fine too
+    unsafe {
+        once.reset();
+        once.reset();
+    }
+    assert_eq!(once.get(), None);
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use alloc::vec;
+use walnut::{
+    mem::{phys_offset, phys_to_virt, read_phys, set_phys_offset, write_phys},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// Pretend physical memory is mapped this far up
+const FAKE_OFFSET: usize = 0x1000_0000;
+
+#[test_case]
+fn test_read_and_write_phys_unaligned() {
+    let mut buf = vec![0u8; 32];
+    let real_offset = phys_offset();
+    unsafe { set_phys_offset(FAKE_OFFSET) };
+
+    // the "physical" address the buffer would be at
+    let pa = buf.as_mut_ptr() as usize - FAKE_OFFSET;
+    assert_eq!(phys_to_virt(pa), buf.as_ptr() as usize);
+    let (word, dword) = unsafe {
+        write_phys(pa + 1, 0x1234_5678u32);
+        write_phys(pa + 7, 0x0102_0304_0506_0708u64);
+        (read_phys::<u32>(pa + 1), read_phys::<u64>(pa + 7))
+    };
+
+    unsafe { set_phys_offset(real_offset) };
+    assert_eq!(word, 0x1234_5678);
+    assert_eq!(dword, 0x0102_0304_0506_0708);
+    assert_eq!(buf[1..5], [0x78, 0x56, 0x34, 0x12]);
+    assert_eq!(buf[7..15], [8, 7, 6, 5, 4, 3, 2, 1]);
+    // nothing around them was touched
+    assert_eq!(buf[0], 0);
+    assert_eq!(buf[5..7], [0, 0]);
+    assert_eq!(buf[15], 0);
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    cpu::{
+        random::{hardware_u64, seed_from, MAX_POLLS},
+        util::my_hart,
+    },
+    fdt::{self, topology::Topology},
+    util::rand::Rng,
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_rng_is_reproducible() {
+    let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+    for _ in 0..100 {
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+    assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+
+    let mut rng = Rng::default();
+    for _ in 0..100 {
+        let n = rng.next_range(10, 20);
+        assert!((10..20).contains(&n));
+    }
+}
+
+#[test_case]
+fn test_hardware_entropy() {
+    let topology = Topology::from_fdt(&fdt::get().unwrap()).unwrap();
+    let hart = unsafe { my_hart() };
+    let has_zkr = topology.harts().any(|h| h.id == hart && h.has_zkr);
+    // the runner turns zkr on, but the test still holds without it
+    if has_zkr {
+        let (a, b) = (hardware_u64().expect("Zkr source failed"), hardware_u64().expect("Zkr source failed"));
+        assert_ne!(a, b);
+    } else {
+        assert_eq!(hardware_u64(), None);
+    }
+}
+
+#[test_case]
+fn test_seed_polling() {
+    const BIST: usize = 0b00 << 30;
+    const WAIT: usize = 0b01 << 30;
+    const ES16: usize = 0b10 << 30;
+    const DEAD: usize = 0b11 << 30;
+
+    // the entropy is in the low 16 bits
+    let mut reads = [BIST, WAIT, ES16 | 0xbeef].into_iter();
+    assert_eq!(seed_from(|| reads.next().unwrap()), Some(0xbeef));
+
+    let mut polls = 0;
+    assert_eq!(seed_from(|| { polls += 1; DEAD }), None);
+    assert_eq!(polls, 1);
+
+    // a source that never has entropy ready is given up on
+    let mut polls = 0;
+    assert_eq!(seed_from(|| { polls += 1; WAIT }), None);
+    assert_eq!(polls, MAX_POLLS);
+}
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    drivers::{self, keyboard, SERIAL},
+    try_println, BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_try_println_before_init() {
+    // booting printed, so put the UART back as it was before that.
+    // the other harts are idle, and nothing is holding on to it
+    unsafe { SERIAL.reset() };
+    assert_eq!(try_println!("dropped {}", 1), Err(core::fmt::Error));
+    assert!(SERIAL.get().is_none());
+
+    drivers::serial();
+    assert_eq!(try_println!(), Ok(()));
+}
+
+#[test_case]
+fn test_received_bytes_are_queued() {
+    assert_eq!(drivers::read_byte(), None);
+    for b in b"ok" {
+        drivers::receive_byte(*b);
+    }
+    assert_eq!(drivers::read_byte(), Some(b'o'));
+    assert_eq!(drivers::read_byte(), Some(b'k'));
+    assert_eq!(drivers::read_byte(), None);
+    while keyboard::read_key().is_some() {}
+}
+
+#[test_case]
+fn test_received_bytes_dropped_when_full() {
+    const QUEUE_SIZE: usize = 256;
+    for i in 0..QUEUE_SIZE + 10 {
+        drivers::receive_byte(b'a' + (i % 26) as u8);
+        // keep the keyboard's own, smaller, queue from filling
+        while keyboard::read_key().is_some() {}
+    }
+    // the oldest bytes are kept, the overflow is lost
+    for i in 0..QUEUE_SIZE {
+        assert_eq!(drivers::read_byte(), Some(b'a' + (i % 26) as u8));
+    }
+    assert_eq!(drivers::read_byte(), None);
+}
@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    drivers::keyboard::{DecodedKey, SpecialKey},
+    shell::{dispatch, parse, parse_number, LineBuffer, MAX_LINE},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_parse() {
+    assert_eq!(parse(""), None);
+    assert_eq!(parse("   "), None);
+    assert_eq!(parse("help"), Some(("help", "")));
+    assert_eq!(parse("  dump   0x1000 16  "), Some(("dump", "0x1000 16")));
+    assert_eq!(parse("dump\t0x1000"), Some(("dump", "0x1000")));
+}
+
+#[test_case]
+fn test_dispatch() {
+    assert!(dispatch("help"));
+    assert!(dispatch("  help  "));
+    // nothing to run, but nothing unknown either
+    assert!(dispatch(""));
+    assert!(!dispatch("frobnicate"));
+    // commands are matched on the whole word
+    assert!(!dispatch("hel"));
+}
+
+#[test_case]
+fn test_parse_number() {
+    assert_eq!(parse_number("42"), Some(42));
+    assert_eq!(parse_number("0x80200000"), Some(0x8020_0000));
+    assert_eq!(parse_number("0x"), None);
+    assert_eq!(parse_number("0xzz"), None);
+    assert_eq!(parse_number("-1"), None);
+    assert_eq!(parse_number(""), None);
+}
+
+fn feed_str(line: &mut LineBuffer, s: &str) {
+    for c in s.chars() {
+        assert!(!line.feed(DecodedKey::Char(c)));
+    }
+}
+
+#[test_case]
+fn test_line_buffer_editing() {
+    let mut line = LineBuffer::new();
+    feed_str(&mut line, "timf");
+    assert!(!line.feed(DecodedKey::Special(SpecialKey::Backspace)));
+    feed_str(&mut line, "e");
+    // keys that don't edit the line are ignored
+    assert!(!line.feed(DecodedKey::Special(SpecialKey::ArrowUp)));
+    assert!(!line.feed(DecodedKey::Char('é')));
+    assert_eq!(line.as_str(), "time");
+    assert!(line.feed(DecodedKey::Special(SpecialKey::Enter)));
+
+    line.clear();
+    assert_eq!(line.as_str(), "");
+    // backspace on an empty line does nothing
+    assert!(!line.feed(DecodedKey::Special(SpecialKey::Backspace)));
+    assert_eq!(line.as_str(), "");
+}
+
+#[test_case]
+fn test_line_buffer_full() {
+    let mut line = LineBuffer::new();
+    for _ in 0..MAX_LINE + 10 {
+        line.feed(DecodedKey::Char('a'));
+    }
+    assert_eq!(line.as_str().len(), MAX_LINE);
+}
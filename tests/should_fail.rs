@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::BootInfo;
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+/// The ordinary failure path: a panic reaching the test panic
+/// handler, which exits QEMU successfully only because this
+/// failure is expected. Without `expect_failure` this test
+/// would fail the binary, as any other failing test does.
+#[test_case]
+fn test_failing_test_fails() {
+    walnut::testing::expect_failure(|| true);
+    let one = core::hint::black_box(1);
+    assert_eq!(one + 1, 3);
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use walnut::{
+    cpu::{
+        self, interrupts,
+        percpu::{self, this_hart},
+        util::my_hart,
+    },
+    task, BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_every_hart_came_online() {
+    let harts = walnut::init::boot_info::get().unwrap().topology.hart_count();
+    // the runner starts QEMU with more than one
+    assert!(harts > 1);
+    assert_eq!(cpu::cpu_count(), harts);
+    // `kmain` already waited for them
+    assert_eq!(cpu::online_harts(), harts);
+}
+
+#[test_case]
+fn test_each_hart_has_its_own_block() {
+    for id in 0..cpu::cpu_count() {
+        assert_eq!(percpu::hart(id).unwrap().id(), id);
+    }
+}
+
+/// A bit for each hart a thread has seen its own block on
+static SEEN: AtomicUsize = AtomicUsize::new(0);
+
+fn all_seen() -> bool {
+    SEEN.load(Ordering::Relaxed).count_ones() as usize == cpu::cpu_count()
+}
+
+/// Check the block we find is for the hart we're on,
+/// until threads have done so on every hart
+fn check_block() {
+    while !all_seen() {
+        // so we aren't moved to another hart in between
+        interrupts::push_off();
+        let hart = unsafe { my_hart() };
+        assert_eq!(this_hart().id(), hart);
+        SEEN.fetch_or(1 << hart, Ordering::Relaxed);
+        interrupts::pop_off();
+        task::yield_now();
+    }
+}
+
+#[test_case]
+fn test_threads_find_their_harts_block() {
+    // the idle harts pick these up as their timers preempt them
+    for _ in 0..cpu::cpu_count() * 2 {
+        task::spawn(check_block);
+    }
+    check_block();
+}
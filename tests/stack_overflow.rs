@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    cpu::stack::{guard_page, GUARD_SIZE},
+    drivers::console::{self, OutputSink},
+    sync::spinlock::SpinLock,
+    testing, BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const CAPTURE_SIZE: usize = 512;
+
+/// Remembers the start of what's printed once it's registered,
+/// in a fixed buffer as sinks are called with the console locked
+struct Capture(SpinLock<([u8; CAPTURE_SIZE], usize)>);
+
+impl OutputSink for Capture {
+    fn write_str(&self, s: &str) {
+        let mut guard = self.0.lock();
+        let (buf, len) = &mut *guard;
+        let n = s.len().min(buf.len() - *len);
+        buf[*len..*len + n].copy_from_slice(&s.as_bytes()[..n]);
+        *len += n;
+    }
+}
+
+static CAPTURE: Capture = Capture(SpinLock::new(([0; CAPTURE_SIZE], 0)));
+
+/// Whether the overflow was reported, at an address
+/// in the overflowing hart's guard page
+fn reported_guard_page() -> bool {
+    let guard = CAPTURE.0.lock();
+    let (buf, len) = &*guard;
+    let Ok(captured) = core::str::from_utf8(&buf[..*len]) else {
+        return false;
+    };
+    let Some((_, report)) = captured.split_once("KERNEL STACK OVERFLOW: hart ") else {
+        return false;
+    };
+    let Some((hart, report)) = report.split_once(" accessed 0x") else {
+        return false;
+    };
+    let Some((addr, _)) = report.split_once(',') else {
+        return false;
+    };
+    let (Ok(hart), Ok(addr)) = (hart.parse::<usize>(), usize::from_str_radix(addr, 16)) else {
+        return false;
+    };
+    (guard_page(hart)..guard_page(hart) + GUARD_SIZE).contains(&addr)
+}
+
+/// Use up a bit more of the stack on every call, forever
+#[allow(unconditional_recursion)]
+fn recurse(depth: usize) -> usize {
+    let frame = core::hint::black_box([depth; 32]);
+    recurse(depth + 1) + frame[depth % 32]
+}
+
+#[test_case]
+fn test_stack_overflow_hits_guard_page() {
+    testing::expect_failure(reported_guard_page);
+    console::register(&CAPTURE).expect("no room for another sink");
+    recurse(0);
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    cpu::canary,
+    println,
+    testing::{exit_qemu, QemuExitCode},
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+/// The last test is expected to end up here
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    if info.message().as_str() == Some("stack smashing detected") {
+        println!("[ok]");
+        exit_qemu(QemuExitCode::Success);
+    }
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_guard_is_set() {
+    // zero so string copies can't reproduce it
+    assert_eq!(canary::guard() & 0xff, 0);
+    assert_ne!(canary::guard(), 0);
+}
+
+#[test_case]
+fn test_stack_chk_fail_halts() {
+    canary::__stack_chk_fail()
+}
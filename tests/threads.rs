@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use walnut::{task, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const ROUNDS: usize = 1000;
+
+static FIRST: AtomicUsize = AtomicUsize::new(0);
+static SECOND: AtomicUsize = AtomicUsize::new(0);
+
+fn count(counter: &AtomicUsize) {
+    for _ in 0..ROUNDS {
+        counter.fetch_add(1, Ordering::Relaxed);
+        core::hint::spin_loop();
+    }
+}
+
+#[test_case]
+fn test_two_threads_both_run() {
+    task::spawn(|| count(&FIRST));
+    task::spawn(|| count(&SECOND));
+
+    // neither thread yields, so this relies on preemption
+    while FIRST.load(Ordering::Relaxed) < ROUNDS || SECOND.load(Ordering::Relaxed) < ROUNDS {
+        core::hint::spin_loop();
+    }
+}
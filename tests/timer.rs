@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{cpu::timer, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_sleep() {
+    let ticks = timer::uptime_ticks();
+    timer::sleep(50);
+    let elapsed = timer::uptime_ticks() - ticks;
+
+    // 50ms is 5 ticks at the default 100Hz, allow for the
+    // partial ticks at either end and QEMU's timing jitter
+    let expected = 50 * timer::frequency() as u64 / 1000;
+    assert!(elapsed + 1 >= expected && elapsed <= expected + 2, "slept for {} ticks, expected {}", elapsed, expected);
+}
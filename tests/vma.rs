@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use walnut::{
+    mem::{
+        addr::VirtAddr,
+        pages::PAGE_SIZE,
+        table::PageTableFlags,
+        vma::{Vma, VmaError, VmaSet},
+    },
+    BootInfo,
+};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const BASE: usize = 0x4000_0000;
+
+fn va(offset: usize) -> VirtAddr {
+    VirtAddr::from_bits(BASE + offset)
+}
+
+fn rw() -> PageTableFlags {
+    PageTableFlags::new()
+        .with(PageTableFlags::READ, true)
+        .with(PageTableFlags::WRITE, true)
+}
+
+fn set() -> VmaSet {
+    VmaSet::new(va(0), va(16 * PAGE_SIZE))
+}
+
+#[test_case]
+fn test_allocate_two_regions() {
+    let mut vmas = set();
+    let heap = vmas.allocate("heap", 2 * PAGE_SIZE, rw()).unwrap();
+    let stack = vmas.allocate("stack", 1, rw()).unwrap();
+
+    assert_eq!(heap, va(0));
+    // rounded up to a page, straight after the heap
+    assert_eq!(stack, va(2 * PAGE_SIZE));
+    assert_eq!(vmas.find(va(PAGE_SIZE + 8)).unwrap().name, "heap");
+    assert_eq!(vmas.find(va(2 * PAGE_SIZE)).unwrap().size(), PAGE_SIZE);
+    assert!(vmas.find(va(3 * PAGE_SIZE)).is_none());
+}
+
+#[test_case]
+fn test_overlap_is_rejected() {
+    let mut vmas = set();
+    vmas.allocate("heap", 4 * PAGE_SIZE, rw()).unwrap();
+
+    let overlapping = Vma {
+        name: "mmap",
+        start: va(3 * PAGE_SIZE),
+        end: va(5 * PAGE_SIZE),
+        flags: rw(),
+    };
+    assert_eq!(vmas.insert(overlapping), Err(VmaError::Overlaps("heap")));
+    assert_eq!(vmas.iter().count(), 1);
+}
+
+#[test_case]
+fn test_gaps_are_reused() {
+    let mut vmas = set();
+    let a = vmas.allocate("a", PAGE_SIZE, rw()).unwrap();
+    vmas.allocate("b", PAGE_SIZE, rw()).unwrap();
+    vmas.remove(a).unwrap();
+
+    assert_eq!(vmas.find_gap(PAGE_SIZE), Some(a));
+    assert_eq!(vmas.find_gap(2 * PAGE_SIZE), Some(va(2 * PAGE_SIZE)));
+    assert_eq!(vmas.allocate("huge", 16 * PAGE_SIZE, rw()), Err(VmaError::NoSpace));
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(walnut::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use walnut::{cpu::timer, testing, BootInfo};
+
+#[no_mangle]
+fn kernel_main(_boot_info: &'static BootInfo) -> ! {
+    test_main();
+    walnut::cpu::wfi_loop()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    walnut::testing::test_panic_handler(info)
+}
+
+const TIMEOUT_TICKS: u64 = 10;
+
+/// When the hung test's deadline passes
+static DEADLINE: AtomicU64 = AtomicU64::new(0);
+
+/// A hung test is only failed by the watchdog, once its time is up
+#[test_case]
+fn test_hung_test_times_out() {
+    testing::expect_failure(|| timer::uptime_ticks() >= DEADLINE.load(Ordering::Relaxed));
+    DEADLINE.store(timer::uptime_ticks() + TIMEOUT_TICKS, Ordering::Relaxed);
+    testing::set_timeout(TIMEOUT_TICKS);
+    loop {
+        core::hint::spin_loop();
+    }
+}